@@ -0,0 +1,48 @@
+use crate::{buffer::Allocator, compare::Compare, counter::Counter, StableBinaryHeap};
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// Builds the heap from a random sequence of pushes and pops, so the item
+/// counters contain the same gaps as in a heap that has been in use
+impl<'a, T, C, N, A> Arbitrary<'a> for StableBinaryHeap<T, C, N, A>
+where
+    T: Arbitrary<'a>,
+    C: Compare<T> + Default,
+    N: Counter,
+    A: Allocator + Default,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut heap = Self::default();
+        // `Some` pushes an item, `None` pops one
+        for op in u.arbitrary_iter::<Option<T>>()? {
+            match op? {
+                Some(item) => heap.push(item),
+                None => {
+                    heap.pop();
+                }
+            }
+        }
+        Ok(heap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compare::MaxComparator, StableBinaryHeap};
+    use arbitrary::{Arbitrary, Unstructured};
+
+    #[test]
+    fn test_arbitrary() {
+        // push 5, push 7, pop, push 5
+        let bytes = [1, 1, 5, 1, 1, 7, 1, 0, 1, 1, 5];
+        let mut u = Unstructured::new(&bytes);
+
+        let heap = StableBinaryHeap::<u8, MaxComparator, u16>::arbitrary(&mut u).unwrap();
+        assert_eq!(heap.len(), 2);
+        assert_eq!(heap.counter(), 3);
+        assert_eq!(heap.into_sorted_vec(), [5, 5]);
+
+        let mut u = Unstructured::new(&[]);
+        let heap = StableBinaryHeap::<u8>::arbitrary(&mut u).unwrap();
+        assert!(heap.is_empty());
+    }
+}
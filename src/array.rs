@@ -0,0 +1,241 @@
+use crate::{
+    compare::{Compare, MaxComparator, MinComparator},
+    item::HeapItem,
+};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug, Display},
+};
+
+/// Stable binary heap storing up to `N` items inline without allocating
+pub struct StableArrayHeap<T, const N: usize, C = MaxComparator> {
+    /// The first `len` slots hold the items in heap order, the rest is empty
+    data: [Option<HeapItem<T>>; N],
+    len: usize,
+    counter: usize,
+    cmp: C,
+}
+
+/// Error returned by `StableArrayHeap::try_push` if the heap is full. Contains
+/// the item that couldn't be pushed
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError<T>(pub T);
+
+impl<T> Debug for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CapacityError").finish_non_exhaustive()
+    }
+}
+
+impl<T> Display for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("heap is full")
+    }
+}
+
+impl<T> core::error::Error for CapacityError<T> {}
+
+impl<T: Ord, const N: usize> StableArrayHeap<T, N> {
+    /// Creates a new empty max-heap
+    #[inline]
+    pub const fn new() -> Self {
+        Self::from_comparator(MaxComparator)
+    }
+}
+
+impl<T: Ord, const N: usize> StableArrayHeap<T, N, MinComparator> {
+    /// Creates a new empty min-heap
+    #[inline]
+    pub const fn new_min() -> Self {
+        Self::from_comparator(MinComparator)
+    }
+}
+
+impl<T, const N: usize, C> StableArrayHeap<T, N, C> {
+    /// Creates a new empty heap ordered by `cmp`
+    #[inline]
+    pub const fn from_comparator(cmp: C) -> Self {
+        Self {
+            data: [const { None }; N],
+            len: 0,
+            counter: 0,
+            cmp,
+        }
+    }
+
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.data[..self.len].fill_with(|| None);
+        self.len = 0;
+        self.counter = 0;
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.data[..self.len].first().map(|i| self.item(i).inner())
+    }
+
+    /// Returns an iterator visiting all items in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.data[..self.len].iter().map(|i| self.item(i).inner())
+    }
+
+    #[inline]
+    fn item<'a>(&self, slot: &'a Option<HeapItem<T>>) -> &'a HeapItem<T> {
+        slot.as_ref().expect("slot below len is empty")
+    }
+}
+
+impl<T, const N: usize, C: Compare<T>> StableArrayHeap<T, N, C> {
+    /// Pushes `item`, failing if the heap already holds `N` items
+    pub fn try_push(&mut self, item: T) -> Result<(), CapacityError<T>> {
+        if self.is_full() {
+            return Err(CapacityError(item));
+        }
+
+        if self.counter == usize::MAX {
+            self.renormalize();
+        }
+        let item = HeapItem::new(item, self.counter);
+        self.counter += 1;
+
+        self.data[self.len] = Some(item);
+        self.len += 1;
+        self.sift_up(self.len - 1);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        self.len -= 1;
+        self.data.swap(0, self.len);
+        let top = self.data[self.len].take();
+        self.sift_down(0);
+        top.map(HeapItem::into_inner)
+    }
+
+    /// Renumbers the counters of all items to `0..len` keeping their relative
+    /// insertion order
+    fn renormalize(&mut self) {
+        let mut order: [usize; N] = core::array::from_fn(|pos| pos);
+        let order = &mut order[..self.len];
+        order.sort_unstable_by_key(|pos| self.item(&self.data[*pos]).counter);
+
+        for (counter, pos) in order.iter().enumerate() {
+            if let Some(item) = &mut self.data[*pos] {
+                item.counter = counter;
+            }
+        }
+        self.counter = self.len;
+    }
+
+    #[inline]
+    fn cmp_at(&self, a: usize, b: usize) -> Ordering {
+        self.cmp
+            .compare_items(self.item(&self.data[a]), self.item(&self.data[b]))
+    }
+
+    fn sift_up(&mut self, mut pos: usize) {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if self.cmp_at(pos, parent) != Ordering::Greater {
+                break;
+            }
+            self.data.swap(pos, parent);
+            pos = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut pos: usize) {
+        loop {
+            let mut child = 2 * pos + 1;
+            if child >= self.len {
+                break;
+            }
+            if child + 1 < self.len && self.cmp_at(child + 1, child) == Ordering::Greater {
+                child += 1;
+            }
+            if self.cmp_at(child, pos) != Ordering::Greater {
+                break;
+            }
+            self.data.swap(pos, child);
+            pos = child;
+        }
+    }
+}
+
+impl<T: Debug, const N: usize, C> Debug for StableArrayHeap<T, N, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord, const N: usize> Default for StableArrayHeap<T, N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CapacityError, StableArrayHeap};
+    use crate::{
+        compare::KeyComparator,
+        test_util::{keyed_items, sorted_desc},
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_array_heap() {
+        let mut heap: StableArrayHeap<_, 64, _> =
+            StableArrayHeap::from_comparator(KeyComparator(|i: &(u32, u32)| i.0));
+        let items = keyed_items(64, 7);
+        for item in &items {
+            heap.try_push(*item).unwrap();
+        }
+        assert!(heap.is_full());
+        assert_eq!(heap.try_push((0, 64)), Err(CapacityError((0, 64))));
+
+        let expected = sorted_desc(items);
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_array_heap_renormalize() {
+        let mut heap = StableArrayHeap::<_, 4>::new();
+        heap.counter = usize::MAX - 1;
+        for i in [1, 2, 1, 1] {
+            heap.try_push(i).unwrap();
+        }
+        assert_eq!(heap.pop(), Some(2));
+        heap.try_push(1).unwrap();
+        assert!(heap.counter < 10);
+        assert_eq!(heap.len(), 4);
+    }
+}
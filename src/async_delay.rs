@@ -0,0 +1,326 @@
+use crate::DelayQueue;
+use core::{
+    fmt::{self, Debug},
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use futures_core::Stream;
+use std::{
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+    time::Instant,
+};
+
+/// Async queue of items that become due at a deadline. `next_expired().await`,
+/// or `next().await` on the `Stream`, resolves once the earliest deadline has
+/// passed. Items with the same deadline are returned in the order they were
+/// pushed.
+///
+/// The queue doesn't depend on a specific runtime. While waiting it wakes the
+/// task from a timer thread, so it works with tokio as well as any other
+/// executor. Each queue starts at most one timer thread, which exits once the
+/// queue is dropped
+pub struct StableDelayQueue<T> {
+    queue: DelayQueue<T, Instant>,
+    /// Started on the first wait
+    timer: Option<Arc<Timer>>,
+}
+
+/// State shared with the timer thread of a queue
+struct Timer {
+    state: Mutex<TimerState>,
+    changed: Condvar,
+}
+
+struct TimerState {
+    /// Waker of the last task waiting for an item
+    waker: Option<Waker>,
+    /// Deadline to wake the waiting task at, `None` if no task is waiting
+    armed: Option<Instant>,
+    /// Set once the queue is dropped
+    shutdown: bool,
+}
+
+impl Timer {
+    fn spawn() -> Arc<Self> {
+        let timer = Arc::new(Self {
+            state: Mutex::new(TimerState {
+                waker: None,
+                armed: None,
+                shutdown: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let shared = Arc::clone(&timer);
+        thread::spawn(move || shared.run());
+        timer
+    }
+
+    /// Sleeps until the armed deadline and wakes the task waiting at that
+    /// time. Re-checks the deadline and waker whenever they change
+    fn run(&self) {
+        let mut state = self.lock();
+        while !state.shutdown {
+            let Some(deadline) = state.armed else {
+                state = self.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+                continue;
+            };
+
+            let now = Instant::now();
+            if deadline > now {
+                state = self
+                    .changed
+                    .wait_timeout(state, deadline - now)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+                continue;
+            }
+
+            state.armed = None;
+            if let Some(waker) = state.waker.take() {
+                drop(state);
+                waker.wake();
+                state = self.lock();
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TimerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Updates the state and lets the timer thread re-check it
+    fn update(&self, f: impl FnOnce(&mut TimerState)) {
+        f(&mut self.lock());
+        self.changed.notify_one();
+    }
+}
+
+impl<T> StableDelayQueue<T> {
+    /// Creates a new empty delay queue
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            queue: DelayQueue::new(),
+            timer: None,
+        }
+    }
+
+    /// Pushes `item` to become due at `deadline`
+    pub fn push(&mut self, item: T, deadline: Instant) {
+        self.queue.push(item, deadline);
+
+        // Moves the timer of a waiting task forward if the new deadline is earlier
+        if let Some(timer) = &self.timer {
+            timer.update(|state| {
+                if state.armed.is_some_and(|armed| deadline < armed) {
+                    state.armed = Some(deadline);
+                }
+            });
+        }
+    }
+
+    /// Pushes `item` to become due after `delay`
+    #[inline]
+    pub fn push_after(&mut self, item: T, delay: Duration) {
+        self.push(item, Instant::now() + delay);
+    }
+
+    /// Waits for the earliest deadline and returns its item. Returns `None`
+    /// right away if the queue is empty
+    #[inline]
+    pub fn next_expired(&mut self) -> impl Future<Output = Option<T>> + '_ {
+        poll_fn(|cx| self.poll_expired(cx))
+    }
+
+    /// Returns the item with the earliest deadline if it is due, otherwise
+    /// registers `cx` to be woken once it is. Returns `Poll::Ready(None)` if
+    /// the queue is empty
+    pub fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let now = Instant::now();
+        if let Some(item) = self.queue.pop_next_due(&now) {
+            return Poll::Ready(Some(item));
+        }
+        let Some(&deadline) = self.queue.next_deadline() else {
+            return Poll::Ready(None);
+        };
+
+        // The waker is replaced on every poll, so the timer always wakes the
+        // task that polled last
+        let timer = self.timer.get_or_insert_with(Timer::spawn);
+        timer.update(|state| {
+            if !state
+                .waker
+                .as_ref()
+                .is_some_and(|w| w.will_wake(cx.waker()))
+            {
+                state.waker = Some(cx.waker().clone());
+            }
+            state.armed = Some(deadline);
+        });
+        Poll::Pending
+    }
+
+    /// Returns the item with the earliest deadline together with its deadline
+    #[inline]
+    pub fn peek(&self) -> Option<(&T, &Instant)> {
+        self.queue.peek()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        if let Some(timer) = &self.timer {
+            timer.update(|state| state.armed = None);
+        }
+    }
+}
+
+impl<T> Drop for StableDelayQueue<T> {
+    fn drop(&mut self) {
+        if let Some(timer) = &self.timer {
+            timer.update(|state| state.shutdown = true);
+        }
+    }
+}
+
+impl<T: Debug> Debug for StableDelayQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StableDelayQueue")
+            .field(&self.queue)
+            .finish()
+    }
+}
+
+impl<T> Default for StableDelayQueue<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Extend<(T, Instant)> for StableDelayQueue<T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (T, Instant)>>(&mut self, iter: I) {
+        for (item, deadline) in iter {
+            self.push(item, deadline);
+        }
+    }
+}
+
+// No field is pinned structurally
+impl<T> Unpin for StableDelayQueue<T> {}
+
+impl<T> Stream for StableDelayQueue<T> {
+    type Item = T;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_expired(cx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableDelayQueue;
+    use alloc::{sync::Arc, vec::Vec};
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll},
+        time::Duration,
+    };
+    use std::{
+        sync::{mpsc, Mutex},
+        task::Wake,
+        thread::{self, Thread},
+        time::Instant,
+    };
+
+    struct ThreadWaker(Thread);
+
+    struct ChannelWaker(Mutex<mpsc::Sender<()>>);
+
+    impl Wake for ChannelWaker {
+        fn wake(self: Arc<Self>) {
+            let _ = self.0.lock().unwrap().send(());
+        }
+    }
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+                return out;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn test_async_delay_queue() {
+        let start = Instant::now();
+        let mut queue = StableDelayQueue::new();
+        for i in 0..6 {
+            queue.push(i, start + Duration::from_millis(20 * (i % 3)));
+        }
+
+        let mut items = Vec::new();
+        while let Some(i) = block_on(queue.next_expired()) {
+            assert!(Instant::now() >= start + Duration::from_millis(20 * (i % 3)));
+            items.push(i);
+        }
+        assert_eq!(items, [0, 3, 1, 4, 2, 5]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_async_delay_queue_wakes_last_poller() {
+        let mut queue = StableDelayQueue::new();
+        queue.push_after(1, Duration::from_millis(30));
+
+        let first = Arc::new(ThreadWaker(thread::current())).into();
+        assert!(queue
+            .poll_expired(&mut Context::from_waker(&first))
+            .is_pending());
+
+        // A different task polls while the first timer is still armed
+        let (sender, receiver) = mpsc::channel();
+        let second = Arc::new(ChannelWaker(Mutex::new(sender))).into();
+        assert!(queue
+            .poll_expired(&mut Context::from_waker(&second))
+            .is_pending());
+
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            queue.poll_expired(&mut Context::from_waker(&second)),
+            Poll::Ready(Some(1))
+        );
+    }
+}
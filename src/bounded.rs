@@ -0,0 +1,154 @@
+use crate::{
+    compare::{Compare, FnComparator, KeyComparator, MaxComparator, MinComparator, Reversed},
+    counter::Counter,
+    StableBinaryHeap,
+};
+use alloc::vec::Vec;
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+};
+
+/// Heap keeping only the `bound` greatest items pushed to it. Once full, the
+/// smallest item gets discarded on push. Of equal items the older ones are kept
+#[derive(Clone)]
+pub struct StableBoundedHeap<T, C = MaxComparator, N = usize> {
+    /// Heap with the smallest item on top, so it can be discarded cheaply
+    heap: StableBinaryHeap<T, Reversed<C>, N>,
+    bound: usize,
+}
+
+impl<T: Ord> StableBoundedHeap<T> {
+    /// Creates a new bounded heap keeping the `bound` greatest items
+    #[inline]
+    pub fn new(bound: usize) -> Self {
+        Self::from_comparator(bound, MaxComparator)
+    }
+}
+
+impl<T: Ord> StableBoundedHeap<T, MinComparator> {
+    /// Creates a new bounded heap keeping the `bound` smallest items
+    #[inline]
+    pub fn new_min(bound: usize) -> Self {
+        Self::from_comparator(bound, MinComparator)
+    }
+}
+
+impl<T, F> StableBoundedHeap<T, FnComparator<F>>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    /// Creates a new bounded heap keeping the `bound` greatest items ordered
+    /// by `cmp`
+    #[inline]
+    pub fn new_by(bound: usize, cmp: F) -> Self {
+        Self::from_comparator(bound, FnComparator(cmp))
+    }
+}
+
+impl<T, K, F> StableBoundedHeap<T, KeyComparator<F>>
+where
+    F: Fn(&T) -> K,
+    K: Ord,
+{
+    /// Creates a new bounded heap keeping the `bound` items with the greatest
+    /// keys returned from `f`
+    #[inline]
+    pub fn new_by_key(bound: usize, f: F) -> Self {
+        Self::from_comparator(bound, KeyComparator(f))
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> StableBoundedHeap<T, C, N> {
+    /// Creates a new bounded heap keeping the `bound` greatest items ordered
+    /// by `cmp`
+    #[inline]
+    pub fn from_comparator(bound: usize, cmp: C) -> Self {
+        let heap = StableBinaryHeap::from_comparator(Reversed(cmp));
+        Self { heap, bound }
+    }
+
+    /// Pushes a new item. If the heap was full, the smallest item gets
+    /// discarded and returned, which might be `item` itself
+    pub fn push(&mut self, item: T) -> Option<T> {
+        if self.heap.len() < self.bound {
+            self.heap.push(item);
+            None
+        } else if self.bound > 0 {
+            // `item` is newer than all other items and therefore only kept
+            // if it is strictly greater than the current smallest one
+            Some(self.heap.push_pop(item))
+        } else {
+            Some(item)
+        }
+    }
+
+    /// Returns the smallest item kept, which is the next one to be discarded
+    #[inline]
+    pub fn peek_min(&self) -> Option<&T> {
+        self.heap.peek()
+    }
+
+    /// Returns the maximum amount of items kept
+    #[inline]
+    pub fn bound(&self) -> usize {
+        self.bound
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Returns `true` if pushing another item discards one
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.heap.len() >= self.bound
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.heap.clear();
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.heap.iter()
+    }
+
+    #[inline]
+    pub fn into_vec(self) -> Vec<T> {
+        self.heap.into_vec()
+    }
+
+    /// Returns all kept items in heap order, greatest first
+    #[inline]
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        let mut vec = self.heap.into_sorted_vec();
+        vec.reverse();
+        vec
+    }
+}
+
+impl<T: Debug, C, N: Debug> Debug for StableBoundedHeap<T, C, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableBoundedHeap")
+            .field("heap", &self.heap)
+            .field("bound", &self.bound)
+            .finish()
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> Extend<T> for StableBoundedHeap<T, C, N> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for i in iter {
+            self.push(i);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Backing storage of the heaps. With the `allocator-api2` feature enabled the
+//! items are stored in an `allocator_api2` `Vec` using the allocator `A`, otherwise
+//! in a regular `Vec` using the global allocator
+
+use alloc::vec::Vec;
+#[cfg(not(feature = "allocator-api2"))]
+use core::marker::PhantomData;
+use core::{
+    fmt::{self, Debug},
+    iter::FusedIterator,
+    ops::{Deref, DerefMut},
+};
+
+#[cfg(feature = "allocator-api2")]
+pub use allocator_api2::alloc::{Allocator, Global};
+
+#[cfg(feature = "allocator-api2")]
+pub use allocator_api2::collections::TryReserveError;
+
+#[cfg(not(feature = "allocator-api2"))]
+pub use alloc::collections::TryReserveError;
+
+#[cfg(feature = "allocator-api2")]
+type RawVec<T, A> = allocator_api2::vec::Vec<T, A>;
+
+#[cfg(feature = "allocator-api2")]
+type RawDrain<'a, T, A> = allocator_api2::vec::Drain<'a, T, A>;
+
+#[cfg(feature = "allocator-api2")]
+type RawIntoIter<T, A> = allocator_api2::vec::IntoIter<T, A>;
+
+#[cfg(not(feature = "allocator-api2"))]
+pub use global::{Allocator, Global};
+
+#[cfg(not(feature = "allocator-api2"))]
+mod global {
+    /// Allocator used for the heap's storage. Enable the `allocator-api2`
+    /// feature to use allocators other than [`Global`]
+    pub trait Allocator: private::Sealed {}
+
+    /// The global memory allocator
+    #[derive(Clone, Copy, Default, Debug)]
+    pub struct Global;
+
+    impl Allocator for Global {}
+
+    mod private {
+        pub trait Sealed {}
+
+        impl Sealed for super::Global {}
+    }
+}
+
+pub(crate) struct Buffer<T, A: Allocator = Global> {
+    #[cfg(feature = "allocator-api2")]
+    vec: RawVec<T, A>,
+
+    #[cfg(not(feature = "allocator-api2"))]
+    vec: Vec<T>,
+    #[cfg(not(feature = "allocator-api2"))]
+    alloc: A,
+}
+
+#[cfg(feature = "allocator-api2")]
+impl<T> Buffer<T> {
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self { vec: RawVec::new() }
+    }
+}
+
+#[cfg(feature = "allocator-api2")]
+impl<T, A: Allocator> Buffer<T, A> {
+    #[inline]
+    pub(crate) fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        let vec = RawVec::with_capacity_in(capacity, alloc);
+        Self { vec }
+    }
+
+    /// Moves the items of `vec` into a buffer allocated with `alloc`
+    #[inline]
+    pub(crate) fn from_vec_in(vec: Vec<T>, alloc: A) -> Self {
+        let mut buffer = Self::with_capacity_in(vec.len(), alloc);
+        buffer.vec.extend(vec);
+        buffer
+    }
+
+    #[inline]
+    pub(crate) fn allocator(&self) -> &A {
+        self.vec.allocator()
+    }
+
+    #[inline]
+    pub(crate) fn drain_all(&mut self) -> Drain<'_, T, A> {
+        Drain {
+            iter: self.vec.drain(..),
+        }
+    }
+
+    /// Returns the items as a `Vec` in the global allocator
+    #[inline]
+    pub(crate) fn into_vec(self) -> Vec<T> {
+        self.vec.into_iter().collect()
+    }
+}
+
+#[cfg(not(feature = "allocator-api2"))]
+impl<T> Buffer<T> {
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self {
+            vec: Vec::new(),
+            alloc: Global,
+        }
+    }
+}
+
+#[cfg(not(feature = "allocator-api2"))]
+impl<T, A: Allocator> Buffer<T, A> {
+    #[inline]
+    pub(crate) fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        let vec = Vec::with_capacity(capacity);
+        Self { vec, alloc }
+    }
+
+    #[inline]
+    pub(crate) fn from_vec_in(vec: Vec<T>, alloc: A) -> Self {
+        Self { vec, alloc }
+    }
+
+    #[inline]
+    pub(crate) fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    #[inline]
+    pub(crate) fn drain_all(&mut self) -> Drain<'_, T, A> {
+        Drain {
+            iter: self.vec.drain(..),
+            alloc: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn into_vec(self) -> Vec<T> {
+        self.vec
+    }
+}
+
+impl<T, A: Allocator> Deref for Buffer<T, A> {
+    #[cfg(feature = "allocator-api2")]
+    type Target = RawVec<T, A>;
+
+    #[cfg(not(feature = "allocator-api2"))]
+    type Target = Vec<T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.vec
+    }
+}
+
+impl<T, A: Allocator> DerefMut for Buffer<T, A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.vec
+    }
+}
+
+impl<T: Clone, A: Allocator + Clone> Clone for Buffer<T, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            vec: self.vec.clone(),
+            #[cfg(not(feature = "allocator-api2"))]
+            alloc: self.alloc.clone(),
+        }
+    }
+}
+
+impl<T: Debug, A: Allocator> Debug for Buffer<T, A> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.vec[..], f)
+    }
+}
+
+/// Owning iterator over the items of a `Buffer`
+pub(crate) type IntoIter<T, A> = <Buffer<T, A> as IntoIterator>::IntoIter;
+
+impl<T, A: Allocator> IntoIterator for Buffer<T, A> {
+    type Item = T;
+
+    #[cfg(feature = "allocator-api2")]
+    type IntoIter = RawIntoIter<T, A>;
+
+    #[cfg(not(feature = "allocator-api2"))]
+    type IntoIter = alloc::vec::IntoIter<T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.vec.into_iter()
+    }
+}
+
+/// Draining iterator over all items of a `Buffer`
+pub(crate) struct Drain<'a, T, A: Allocator> {
+    #[cfg(feature = "allocator-api2")]
+    iter: RawDrain<'a, T, A>,
+
+    #[cfg(not(feature = "allocator-api2"))]
+    iter: alloc::vec::Drain<'a, T>,
+    #[cfg(not(feature = "allocator-api2"))]
+    alloc: PhantomData<A>,
+}
+
+impl<'a, T, A: Allocator> Iterator for Drain<'a, T, A> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T, A: Allocator> DoubleEndedIterator for Drain<'a, T, A> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back()
+    }
+}
+
+impl<'a, T, A: Allocator> ExactSizeIterator for Drain<'a, T, A> {}
+
+impl<'a, T, A: Allocator> FusedIterator for Drain<'a, T, A> {}
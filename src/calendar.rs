@@ -0,0 +1,325 @@
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+    time::Duration,
+};
+
+/// Timestamp of the events in a `CalendarQueue`
+pub trait Timestamp: Copy {
+    /// Total order of the timestamps
+    fn cmp_time(&self, other: &Self) -> Ordering;
+
+    /// Position of the timestamp on the calendar, which has to be
+    /// non-decreasing with respect to `cmp_time`
+    fn to_f64(self) -> f64;
+}
+
+impl Timestamp for f64 {
+    #[inline]
+    fn cmp_time(&self, other: &Self) -> Ordering {
+        self.total_cmp(other)
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Timestamp for f32 {
+    #[inline]
+    fn cmp_time(&self, other: &Self) -> Ordering {
+        self.total_cmp(other)
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        f64::from(self)
+    }
+}
+
+impl Timestamp for Duration {
+    #[inline]
+    fn cmp_time(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self.as_secs_f64()
+    }
+}
+
+const MIN_BUCKETS: usize = 2;
+
+/// Amount of events used to estimate the bucket width on resizes
+const WIDTH_SAMPLE: usize = 25;
+
+/// Priority queue for discrete event simulations returning the event with the
+/// earliest timestamp first. Simultaneous events are returned in the order
+/// they were pushed. Events are sorted into buckets that each cover a slot of
+/// time, repeating like the days of a calendar, which makes pushing and
+/// popping O(1) on average as long as the timestamps are spread evenly
+#[derive(Clone)]
+pub struct CalendarQueue<T, I = f64> {
+    /// Every bucket is sorted by timestamp and in insertion order for equal
+    /// timestamps
+    buckets: Vec<VecDeque<(I, T)>>,
+    /// Length of time covered by one bucket
+    width: f64,
+    /// Slot of the earliest event, slot `s` is stored in bucket
+    /// `s mod buckets.len()`
+    slot: i64,
+    len: usize,
+}
+
+impl<T, I: Timestamp> CalendarQueue<T, I> {
+    /// Creates a new empty calendar queue
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..MIN_BUCKETS).map(|_| VecDeque::new()).collect(),
+            width: 1.0,
+            slot: 0,
+            len: 0,
+        }
+    }
+
+    /// Pushes `item` to happen at `time`
+    pub fn push(&mut self, item: T, time: I) {
+        let slot = self.slot_of(time);
+        if slot < self.slot || self.is_empty() {
+            self.slot = slot;
+        }
+
+        let bucket = self.bucket_of(slot);
+        let bucket = &mut self.buckets[bucket];
+        let pos = bucket.partition_point(|(t, _)| t.cmp_time(&time) != Ordering::Greater);
+        bucket.insert(pos, (time, item));
+        self.len += 1;
+
+        if self.len > 2 * self.buckets.len() {
+            self.resize(2 * self.buckets.len());
+        }
+    }
+
+    /// Removes the earliest event and returns it together with its timestamp
+    pub fn pop(&mut self) -> Option<(T, I)> {
+        let event = self.pop_event()?;
+        if self.buckets.len() > MIN_BUCKETS && self.len < self.buckets.len() / 2 {
+            self.resize(self.buckets.len() / 2);
+        }
+        Some(event)
+    }
+
+    /// Returns the earliest event together with its timestamp
+    #[inline]
+    pub fn peek(&self) -> Option<(&T, &I)> {
+        let (bucket, _) = self.find_next()?;
+        self.buckets[bucket]
+            .front()
+            .map(|(time, item)| (item, time))
+    }
+
+    /// Returns the timestamp of the earliest event
+    #[inline]
+    pub fn next_time(&self) -> Option<&I> {
+        self.peek().map(|(_, time)| time)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Returns an iterator over all events and their timestamps in arbitrary
+    /// order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&T, &I)> {
+        self.buckets
+            .iter()
+            .flatten()
+            .map(|(time, item)| (item, time))
+    }
+
+    /// Removes the earliest event without resizing
+    fn pop_event(&mut self) -> Option<(T, I)> {
+        let (bucket, slot) = self.find_next()?;
+        self.slot = slot;
+        self.len -= 1;
+        self.buckets[bucket]
+            .pop_front()
+            .map(|(time, item)| (item, time))
+    }
+
+    /// Returns the bucket and slot of the earliest event. Walks the buckets
+    /// for one year starting at the current slot and falls back to searching
+    /// the earliest event of all buckets if there are no events in that year
+    fn find_next(&self) -> Option<(usize, i64)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        for slot in self.slot..self.slot.saturating_add(self.buckets.len() as i64) {
+            let bucket = self.bucket_of(slot);
+            if let Some((time, _)) = self.buckets[bucket].front() {
+                if self.slot_of(*time) <= slot {
+                    return Some((bucket, slot));
+                }
+            }
+        }
+
+        let (bucket, time) = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter_map(|(bucket, events)| events.front().map(|(time, _)| (bucket, time)))
+            .min_by(|a, b| a.1.cmp_time(b.1))?;
+        Some((bucket, self.slot_of(*time)))
+    }
+
+    /// Moves all events into `buckets` buckets, estimating a new bucket width
+    /// from the separation of the earliest events
+    fn resize(&mut self, buckets: usize) {
+        let mut events = Vec::with_capacity(self.len);
+        while let Some((item, time)) = self.pop_event() {
+            events.push((time, item));
+        }
+
+        if let Some(width) = estimate_width(&events) {
+            self.width = width;
+        }
+        self.buckets = (0..buckets).map(|_| VecDeque::new()).collect();
+        self.slot = events.first().map_or(0, |(time, _)| self.slot_of(*time));
+        self.len = events.len();
+
+        // Events are sorted, so appending keeps every bucket sorted and the
+        // insertion order of simultaneous events
+        for (time, item) in events {
+            let bucket = self.bucket_of(self.slot_of(time));
+            self.buckets[bucket].push_back((time, item));
+        }
+    }
+
+    /// Rounds down manually since `f64::floor` needs `std`
+    #[inline]
+    fn slot_of(&self, time: I) -> i64 {
+        let slot = time.to_f64() / self.width;
+        let truncated = slot as i64;
+        if (truncated as f64) > slot {
+            truncated - 1
+        } else {
+            truncated
+        }
+    }
+
+    #[inline]
+    fn bucket_of(&self, slot: i64) -> usize {
+        slot.rem_euclid(self.buckets.len() as i64) as usize
+    }
+}
+
+/// Returns three times the average separation of the first sorted `events`,
+/// ignoring separations more than twice as large as the average
+fn estimate_width<T, I: Timestamp>(events: &[(I, T)]) -> Option<f64> {
+    let sample = &events[..events.len().min(WIDTH_SAMPLE)];
+    let gaps = || sample.windows(2).map(|w| w[1].0.to_f64() - w[0].0.to_f64());
+
+    let average = gaps().sum::<f64>() / gaps().count().max(1) as f64;
+    let (sum, count) = gaps()
+        .filter(|gap| *gap <= 2.0 * average)
+        .fold((0.0, 0), |(sum, count), gap| (sum + gap, count + 1));
+    let width = 3.0 * sum / count.max(1) as f64;
+    (width.is_finite() && width > 0.0).then_some(width)
+}
+
+impl<T: Debug, I: Timestamp + Debug> Debug for CalendarQueue<T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(item, time)| (time, item)))
+            .finish()
+    }
+}
+
+impl<T, I: Timestamp> Default for CalendarQueue<T, I> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, I: Timestamp> Extend<(T, I)> for CalendarQueue<T, I> {
+    #[inline]
+    fn extend<It: IntoIterator<Item = (T, I)>>(&mut self, iter: It) {
+        for (item, time) in iter {
+            self.push(item, time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CalendarQueue;
+    use crate::test_util::scatter;
+    use crate::DelayQueue;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[test]
+    fn test_calendar_queue() {
+        let events: Vec<_> = (0..1000u32)
+            .map(|i| (i, f64::from(scatter(i, 40)) * 0.25))
+            .collect();
+        let mut queue = CalendarQueue::new();
+        queue.extend(events.iter().copied());
+        assert_eq!(queue.len(), 1000);
+
+        let mut expected = events;
+        expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+        assert_eq!(queue.peek(), Some((&expected[0].0, &0.0)));
+        let out: Vec<_> = core::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(out, expected);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_calendar_queue_simulation() {
+        let mut queue = CalendarQueue::new();
+        let mut reference = DelayQueue::new();
+        let mut now = Duration::ZERO;
+
+        for i in 0..5000u32 {
+            // Mostly schedules into the future, sometimes into the past
+            let offset = Duration::from_millis(u64::from(scatter(i, 500)));
+            let time = if i % 50 == 0 {
+                now.saturating_sub(offset)
+            } else {
+                now + offset
+            };
+            queue.push(i, time);
+            reference.push(i, time);
+
+            if i % 2 == 0 {
+                let (item, time) = queue.pop().unwrap();
+                assert_eq!(Some(item), reference.pop_next_due(&Duration::MAX));
+                now = time;
+            }
+        }
+
+        assert_eq!(queue.len(), reference.len());
+        let out: Vec<_> = core::iter::from_fn(|| queue.pop()).map(|e| e.0).collect();
+        let expected: Vec<_> = reference.pop_due(&Duration::MAX).collect();
+        assert_eq!(out, expected);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{wait::wait_while, StableBinaryHeap};
+use alloc::sync::Arc;
+use core::{
+    fmt::{self, Debug},
+    time::Duration,
+};
+use std::sync::{
+    mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError},
+    Condvar, Mutex, MutexGuard, PoisonError,
+};
+
+/// Creates a channel whose receiver always gets the greatest pending message.
+/// Messages comparing equal are received in the order they were sent
+pub fn stable_priority_channel<T: Ord>() -> (Sender<T>, Receiver<T>) {
+    channel(None)
+}
+
+/// Creates a priority channel holding at most `bound` pending messages. Sending
+/// blocks while the channel is full
+pub fn bounded_stable_priority_channel<T: Ord>(bound: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(bound > 0, "bound must be greater than 0");
+    channel(Some(bound))
+}
+
+fn channel<T: Ord>(bound: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            heap: StableBinaryHeap::new(),
+            senders: 1,
+            receiver: true,
+        }),
+        bound,
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+    });
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+    (sender, Receiver { shared })
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    bound: Option<usize>,
+    /// Notified when a message is sent or the last sender is dropped
+    not_empty: Condvar,
+    /// Notified when a message is received or the receiver is dropped
+    not_full: Condvar,
+}
+
+struct State<T> {
+    heap: StableBinaryHeap<T>,
+    senders: usize,
+    /// `false` once the receiver has been dropped
+    receiver: bool,
+}
+
+impl<T> Shared<T> {
+    /// Locks the state. The heap can't be left in an unsafe state by a panic,
+    /// so poisoning is ignored
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Ord> Shared<T> {
+    #[inline]
+    fn is_full(&self, state: &State<T>) -> bool {
+        self.bound.is_some_and(|bound| state.heap.len() >= bound)
+    }
+}
+
+/// Sending half of a priority channel. Can be cloned to send from multiple
+/// threads
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Ord> Sender<T> {
+    /// Sends `msg`, blocking while a bounded channel is full. Fails if the
+    /// receiver has been dropped
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let shared = &self.shared;
+        let (mut state, _) = wait_while(&shared.not_full, shared.lock(), None, |state| {
+            state.receiver && shared.is_full(state)
+        });
+        if !state.receiver {
+            return Err(SendError(msg));
+        }
+
+        state.heap.push(msg);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Sends `msg` without blocking
+    pub fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.lock();
+        if !state.receiver {
+            return Err(TrySendError::Disconnected(msg));
+        }
+        if self.shared.is_full(&state) {
+            return Err(TrySendError::Full(msg));
+        }
+
+        state.heap.push(msg);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            self.shared.not_empty.notify_all();
+        }
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+/// Receiving half of a priority channel
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Ord> Receiver<T> {
+    /// Receives the greatest pending message, blocking while the channel is
+    /// empty. Fails once the channel is empty and all senders have been dropped
+    #[inline]
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.recv_wait(None).map_err(|_| RecvError)
+    }
+
+    /// Receives the greatest pending message without blocking
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.lock();
+        match self.pop(&mut state) {
+            Some(msg) => Ok(msg),
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Receives the greatest pending message, waiting at most `timeout` for one
+    /// to be sent
+    #[inline]
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_wait(Some(timeout))
+    }
+
+    /// Returns an iterator receiving messages until all senders have been
+    /// dropped
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.recv().ok())
+    }
+
+    /// Returns the amount of pending messages
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.shared.lock().heap.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Waits while the channel is empty and a sender is left, then receives
+    /// the greatest pending message
+    fn recv_wait(&self, timeout: Option<Duration>) -> Result<T, RecvTimeoutError> {
+        let (mut state, timed_out) = wait_while(
+            &self.shared.not_empty,
+            self.shared.lock(),
+            timeout,
+            |state| state.heap.is_empty() && state.senders > 0,
+        );
+        match self.pop(&mut state) {
+            Some(msg) => Ok(msg),
+            None if timed_out => Err(RecvTimeoutError::Timeout),
+            None => Err(RecvTimeoutError::Disconnected),
+        }
+    }
+
+    #[inline]
+    fn pop(&self, state: &mut State<T>) -> Option<T> {
+        let msg = state.heap.pop()?;
+        self.shared.not_full.notify_one();
+        Some(msg)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver = false;
+        self.shared.not_full.notify_all();
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bounded_stable_priority_channel, stable_priority_channel};
+    use crate::test_util::scatter;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::sync::mpsc::{RecvTimeoutError, TryRecvError, TrySendError};
+
+    #[test]
+    fn test_priority_channel() {
+        let (tx, rx) = stable_priority_channel();
+        std::thread::scope(|s| {
+            for thread in 0..4u32 {
+                let tx = tx.clone();
+                s.spawn(move || {
+                    for i in 0..100u32 {
+                        tx.send(Msg(scatter(i, 5), thread, i)).unwrap();
+                    }
+                });
+            }
+        });
+        drop(tx);
+
+        let msgs: Vec<_> = rx.iter().collect();
+        assert_eq!(msgs.len(), 400);
+        for pair in msgs.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            assert!(a.0 >= b.0);
+            if a.0 == b.0 && a.1 == b.1 {
+                assert!(a.2 < b.2);
+            }
+        }
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_bounded_priority_channel() {
+        let (tx, rx) = bounded_stable_priority_channel(2);
+        tx.send(1).unwrap();
+        tx.send(3).unwrap();
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+
+        std::thread::scope(|s| {
+            s.spawn(|| tx.send(2).unwrap());
+            std::thread::sleep(Duration::from_millis(10));
+            assert_eq!(rx.recv(), Ok(3));
+        });
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(1)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        drop(rx);
+        assert!(tx.send(4).is_err());
+    }
+
+    /// Compares only by the first field
+    #[derive(Debug)]
+    struct Msg(u32, u32, u32);
+
+    impl PartialEq for Msg {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Msg {}
+
+    impl PartialOrd for Msg {
+        fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Msg {
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::item::HeapItem;
+use core::cmp::Ordering;
+
+/// Defines the priority order of a `StableBinaryHeap`. The heap always pops
+/// the element that compares as greatest, equal elements are returned in
+/// insertion order unless the comparator is wrapped in [`Lifo`]
+pub trait Compare<T> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+
+    /// Compares two heap items
+    #[inline]
+    fn compare_items<N: Ord>(&self, a: &HeapItem<T, N>, b: &HeapItem<T, N>) -> Ordering {
+        self.compare_parts(&a.inner, &a.counter, &b.inner, &b.counter)
+    }
+
+    /// Compares two items stored apart from their counters. The default
+    /// implementation orders equal items by their counter so that the older
+    /// item is the greater one
+    #[inline]
+    fn compare_parts<N: Ord>(&self, a: &T, a_counter: &N, b: &T, b_counter: &N) -> Ordering {
+        self.compare(a, b).then_with(|| b_counter.cmp(a_counter))
+    }
+}
+
+/// Orders elements by their `Ord` implementation, resulting in a max-heap
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MaxComparator;
+
+impl<T: Ord> Compare<T> for MaxComparator {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Orders elements by their reversed `Ord` implementation, resulting in a min-heap
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MinComparator;
+
+impl<T: Ord> Compare<T> for MinComparator {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+/// Direction marker for max-heaps, `StableBinaryHeap<T, Max>` is the same
+/// type as `StableBinaryHeap<T>`
+pub type Max = MaxComparator;
+
+/// Direction marker for min-heaps, `StableBinaryHeap<T, Min>` is the same
+/// type as `StableBinaryMinHeap<T>`
+pub type Min = MinComparator;
+
+/// Orders elements by a user defined comparison function
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FnComparator<F>(pub F);
+
+impl<T, F> Compare<T> for FnComparator<F>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a, b)
+    }
+}
+
+/// Orders elements by the key extracted with a user defined function
+#[derive(Clone, Copy, Default, Debug)]
+pub struct KeyComparator<F>(pub F);
+
+impl<T, K, F> Compare<T> for KeyComparator<F>
+where
+    F: Fn(&T) -> K,
+    K: Ord,
+{
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+}
+
+/// Orders elements by `C` and breaks ties between elements `C` considers equal
+/// with `D`. Elements equal under both are ordered by the tie-break policy of
+/// `C`, so `ThenComparator(Lifo(c), d)` returns them newest first
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ThenComparator<C, D>(pub C, pub D);
+
+impl<T, C: Compare<T>, D: Compare<T>> Compare<T> for ThenComparator<C, D> {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(a, b).then_with(|| self.1.compare(a, b))
+    }
+
+    #[inline]
+    fn compare_parts<N: Ord>(&self, a: &T, a_counter: &N, b: &T, b_counter: &N) -> Ordering {
+        self.0
+            .compare(a, b)
+            .then_with(|| self.1.compare(a, b))
+            .then_with(|| self.0.compare_parts(a, a_counter, b, b_counter))
+    }
+}
+
+/// Returns equal elements in insertion order. This is what all comparators do
+/// by default, so `Fifo` only documents the intent at the type level
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Fifo<C = MaxComparator>(pub C);
+
+impl<T, C: Compare<T>> Compare<T> for Fifo<C> {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(a, b)
+    }
+}
+
+/// Returns equal elements in reversed insertion order, newest first
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Lifo<C = MaxComparator>(pub C);
+
+impl<T, C: Compare<T>> Compare<T> for Lifo<C> {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(a, b)
+    }
+
+    #[inline]
+    fn compare_parts<N: Ord>(&self, a: &T, a_counter: &N, b: &T, b_counter: &N) -> Ordering {
+        self.0.compare(a, b).then_with(|| a_counter.cmp(b_counter))
+    }
+}
+
+/// Reverses the whole stable order of `C`, including the order of equal items
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct Reversed<C>(pub C);
+
+impl<T, C: Compare<T>> Compare<T> for Reversed<C> {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(b, a)
+    }
+
+    #[inline]
+    fn compare_parts<N: Ord>(&self, a: &T, a_counter: &N, b: &T, b_counter: &N) -> Ordering {
+        self.0.compare_parts(b, b_counter, a, a_counter)
+    }
+}
+
+/// Reverses the order of `C` while equal items keep the tie-break policy of `C`
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct Ascending<C>(pub C);
+
+impl<T, C: Compare<T>> Compare<T> for Ascending<C> {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(b, a)
+    }
+
+    #[inline]
+    fn compare_parts<N: Ord>(&self, a: &T, a_counter: &N, b: &T, b_counter: &N) -> Ordering {
+        self.0
+            .compare(b, a)
+            .then_with(|| self.0.compare_parts(a, a_counter, b, b_counter))
+    }
+}
@@ -0,0 +1,385 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    counter::Counter,
+    wait::wait_while,
+    StableBinaryHeap,
+};
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug},
+    time::Duration,
+};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+/// Stable binary heap that can be shared between threads. Items are numbered
+/// while holding the heap's lock, so equal items are popped in the global
+/// order in which their pushes completed, regardless of the pushing thread
+pub struct StableConcurrentHeap<T, C = MaxComparator, N = usize> {
+    heap: Mutex<StableBinaryHeap<T, C, N>>,
+    /// Notified whenever items are pushed
+    pushed: Condvar,
+}
+
+impl<T: Ord> StableConcurrentHeap<T> {
+    /// Creates a new empty heap
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_heap(StableBinaryHeap::new())
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> StableConcurrentHeap<T, C, N> {
+    /// Creates a new empty heap ordered by `cmp`
+    #[inline]
+    pub fn from_comparator(cmp: C) -> Self {
+        Self::from_heap(StableBinaryHeap::from_comparator(cmp))
+    }
+
+    /// Wraps an existing heap, keeping the order of its items
+    #[inline]
+    pub fn from_heap(heap: StableBinaryHeap<T, C, N>) -> Self {
+        Self {
+            heap: Mutex::new(heap),
+            pushed: Condvar::new(),
+        }
+    }
+
+    #[inline]
+    pub fn push(&self, item: T) {
+        self.lock().push(item);
+        self.pushed.notify_one();
+    }
+
+    /// Pushes all items of `iter` while holding the lock once, so they stay
+    /// consecutive in insertion order
+    pub fn push_batch<I: IntoIterator<Item = T>>(&self, iter: I) {
+        self.lock().extend(iter);
+        self.pushed.notify_all();
+    }
+
+    #[inline]
+    pub fn pop(&self) -> Option<T> {
+        self.lock().pop()
+    }
+
+    /// Pops the greatest item, waiting up to `timeout` for an item to be
+    /// pushed if the heap is empty
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let (mut heap, _) = wait_while(&self.pushed, self.lock(), Some(timeout), |heap| {
+            heap.is_empty()
+        });
+        heap.pop()
+    }
+
+    /// Removes the `k` greatest items while holding the lock once and returns
+    /// them in heap order
+    #[inline]
+    pub fn pop_k(&self, k: usize) -> Vec<T> {
+        self.lock().pop_k(k)
+    }
+
+    /// Calls `f` with the greatest item
+    #[inline]
+    pub fn peek_with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(Option<&T>) -> R,
+    {
+        f(self.lock().peek())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Returns the underlying heap
+    #[inline]
+    pub fn into_inner(self) -> StableBinaryHeap<T, C, N> {
+        self.heap
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the heap. A panic while the lock was held (e.g. in the comparator)
+    /// can't leave the heap in an unsafe state, so poisoning is ignored
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, StableBinaryHeap<T, C, N>> {
+        self.heap.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Debug, C: Compare<T>, N: Counter> Debug for StableConcurrentHeap<T, C, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableConcurrentHeap")
+            .field("heap", &*self.lock())
+            .finish()
+    }
+}
+
+impl<T: Ord> Default for StableConcurrentHeap<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bounded stable binary heap for producer/consumer setups. `push` blocks
+/// while the heap is full and `pop` blocks while it is empty. Equal items are
+/// popped in the order their pushes completed.
+///
+/// After `close` pushing fails and popping only returns the remaining items,
+/// which lets consumers shut down once the heap has been drained
+pub struct SyncStableHeap<T, C = MaxComparator, N = usize> {
+    state: Mutex<SyncState<T, C, N>>,
+    bound: usize,
+    /// Notified when an item is pushed or the heap is closed
+    not_empty: Condvar,
+    /// Notified when an item is popped or the heap is closed
+    not_full: Condvar,
+}
+
+struct SyncState<T, C, N> {
+    heap: StableBinaryHeap<T, C, N>,
+    closed: bool,
+}
+
+impl<T: Ord> SyncStableHeap<T> {
+    /// Creates a new empty heap holding at most `bound` items
+    #[inline]
+    pub fn new(bound: usize) -> Self {
+        Self::from_comparator(bound, MaxComparator)
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> SyncStableHeap<T, C, N> {
+    /// Creates a new empty heap holding at most `bound` items ordered by `cmp`
+    pub fn from_comparator(bound: usize, cmp: C) -> Self {
+        assert!(bound > 0, "bound must be greater than 0");
+        Self {
+            state: Mutex::new(SyncState {
+                heap: StableBinaryHeap::with_capacity_and_comparator(bound, cmp),
+                closed: false,
+            }),
+            bound,
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        }
+    }
+
+    /// Pushes `item`, blocking while the heap is full. Returns the item back
+    /// if the heap has been closed
+    pub fn push(&self, item: T) -> Result<(), T> {
+        let (mut state, _) = wait_while(&self.not_full, self.lock(), None, |state| {
+            !state.closed && state.heap.len() >= self.bound
+        });
+        if state.closed {
+            return Err(item);
+        }
+
+        state.heap.push(item);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Pushes `item` without blocking. Returns the item back if the heap is
+    /// full or has been closed
+    pub fn try_push(&self, item: T) -> Result<(), T> {
+        let mut state = self.lock();
+        if state.closed || state.heap.len() >= self.bound {
+            return Err(item);
+        }
+
+        state.heap.push(item);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Pops the greatest item, blocking while the heap is empty. Returns `None`
+    /// once the heap is closed and empty
+    #[inline]
+    pub fn pop(&self) -> Option<T> {
+        self.pop_wait(None)
+    }
+
+    /// Pops the greatest item without blocking
+    #[inline]
+    pub fn try_pop(&self) -> Option<T> {
+        self.pop_locked(&mut self.lock())
+    }
+
+    /// Pops the greatest item, waiting up to `timeout` for an item to be
+    /// pushed if the heap is empty
+    #[inline]
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        self.pop_wait(Some(timeout))
+    }
+
+    /// Closes the heap, waking all blocked producers and consumers
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().heap.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lock().heap.is_empty()
+    }
+
+    /// Returns the maximum amount of items the heap holds
+    #[inline]
+    pub fn bound(&self) -> usize {
+        self.bound
+    }
+
+    /// Waits while the heap is empty and open, then pops the greatest item
+    fn pop_wait(&self, timeout: Option<Duration>) -> Option<T> {
+        let (mut state, _) = wait_while(&self.not_empty, self.lock(), timeout, |state| {
+            !state.closed && state.heap.is_empty()
+        });
+        self.pop_locked(&mut state)
+    }
+
+    #[inline]
+    fn pop_locked(&self, state: &mut SyncState<T, C, N>) -> Option<T> {
+        let item = state.heap.pop()?;
+        self.not_full.notify_one();
+        Some(item)
+    }
+
+    /// Locks the state, ignoring poisoning like `StableConcurrentHeap::lock`
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, SyncState<T, C, N>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Debug, C: Compare<T>, N: Counter> Debug for SyncStableHeap<T, C, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("SyncStableHeap")
+            .field("heap", &state.heap)
+            .field("bound", &self.bound)
+            .field("closed", &state.closed)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StableConcurrentHeap, SyncStableHeap};
+    use crate::{compare::KeyComparator, test_util::scatter};
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[test]
+    fn test_concurrent_heap() {
+        let heap: StableConcurrentHeap<_, _> =
+            StableConcurrentHeap::from_comparator(KeyComparator(|i: &(u32, usize, u32)| i.0));
+        std::thread::scope(|s| {
+            for thread in 0..4 {
+                let heap = &heap;
+                s.spawn(move || {
+                    for i in 0..500u32 {
+                        heap.push((scatter(i, 7), thread, i));
+                    }
+                });
+            }
+        });
+        assert_eq!(heap.len(), 2000);
+
+        // Equal items of each thread must come out in the thread's push order
+        let mut last = [[None; 4]; 7];
+        let mut prev_key = u32::MAX;
+        while let Some((key, thread, i)) = heap.pop() {
+            assert!(key <= prev_key);
+            prev_key = key;
+
+            let last = &mut last[key as usize][thread];
+            assert!(last.is_none_or(|last| last < i));
+            *last = Some(i);
+        }
+    }
+
+    #[test]
+    fn test_pop_timeout() {
+        let heap = StableConcurrentHeap::new();
+        assert_eq!(heap.pop_timeout(Duration::from_millis(1)), None);
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(10));
+                heap.push_batch([1, 3, 2]);
+            });
+            assert_eq!(heap.pop_timeout(Duration::from_secs(10)), Some(3));
+        });
+        assert_eq!(heap.pop_k(5), Vec::from([2, 1]));
+    }
+
+    #[test]
+    fn test_sync_heap() {
+        let heap: SyncStableHeap<_, _> =
+            SyncStableHeap::from_comparator(4, KeyComparator(|i: &(u32, u32)| i.0));
+        let popped = std::thread::scope(|s| {
+            let consumer = s.spawn(|| {
+                let mut popped = Vec::new();
+                while let Some(i) = heap.pop() {
+                    popped.push(i);
+                }
+                popped
+            });
+
+            for i in 0..200u32 {
+                heap.push((scatter(i, 3), i)).unwrap();
+                assert!(heap.len() <= 4);
+            }
+            heap.close();
+            consumer.join().unwrap()
+        });
+
+        assert_eq!(popped.len(), 200);
+        for pair in popped.windows(2) {
+            if pair[0].0 == pair[1].0 {
+                assert!(pair[0].1 < pair[1].1);
+            }
+        }
+        assert_eq!(heap.push((0, 0)), Err((0, 0)));
+    }
+
+    #[test]
+    fn test_sync_heap_full() {
+        let heap = SyncStableHeap::new(2);
+        heap.push(1).unwrap();
+        heap.push(2).unwrap();
+        assert_eq!(heap.try_push(3), Err(3));
+
+        std::thread::scope(|s| {
+            s.spawn(|| heap.push(3).unwrap());
+            std::thread::sleep(Duration::from_millis(10));
+            assert_eq!(heap.pop(), Some(2));
+        });
+        assert_eq!(heap.try_pop(), Some(3));
+        assert_eq!(heap.pop_timeout(Duration::from_millis(1)), Some(1));
+        assert_eq!(heap.pop_timeout(Duration::from_millis(1)), None);
+    }
+}
@@ -0,0 +1,198 @@
+use alloc::collections::BTreeMap;
+use core::fmt::{self, Debug};
+
+/// Max-heap storing equal items only once together with how often they were
+/// pushed. Meant for items whose `Ord` equality means identity, like integers
+/// or strings, for which returning the duplicates one after another is the
+/// same as returning them in insertion order.
+///
+/// Items that compare equal without being identical must not be pushed. Only
+/// the first pushed of them is stored and returned for all of them, so their
+/// payloads would get lost. Use `StableBinaryHeap` for those
+#[derive(Clone)]
+pub struct StableCountedHeap<T> {
+    items: BTreeMap<T, usize>,
+    len: usize,
+}
+
+impl<T: Ord> StableCountedHeap<T> {
+    /// Creates a new empty heap
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            items: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, item: T) {
+        self.push_n(item, 1);
+    }
+
+    /// Pushes `item` `n` times
+    pub fn push_n(&mut self, item: T, n: usize) {
+        if n == 0 {
+            return;
+        }
+        *self.items.entry(item).or_insert(0) += n;
+        self.len += n;
+    }
+
+    /// Removes one instance of the greatest item and returns it
+    pub fn pop(&mut self) -> Option<T>
+    where
+        T: Clone,
+    {
+        let mut entry = self.items.last_entry()?;
+        self.len -= 1;
+        if *entry.get() > 1 {
+            *entry.get_mut() -= 1;
+            Some(entry.key().clone())
+        } else {
+            Some(entry.remove_entry().0)
+        }
+    }
+
+    /// Removes all instances of the greatest item and returns it together with
+    /// how often it was pushed
+    pub fn pop_all(&mut self) -> Option<(T, usize)> {
+        let (item, count) = self.items.pop_last()?;
+        self.len -= count;
+        Some((item, count))
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.items.last_key_value().map(|(item, _)| item)
+    }
+
+    /// Returns how often an item equal to `item` is stored
+    #[inline]
+    pub fn count(&self, item: &T) -> usize {
+        self.items.get(item).copied().unwrap_or(0)
+    }
+
+    /// Returns the amount of items, counting duplicates
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the amount of distinct items
+    #[inline]
+    pub fn distinct_len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.len = 0;
+    }
+
+    /// Returns an iterator over the distinct items and their counts in heap
+    /// order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&T, usize)> {
+        self.items.iter().rev().map(|(item, count)| (item, *count))
+    }
+}
+
+impl<T: Debug + Ord> Debug for StableCountedHeap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord> Default for StableCountedHeap<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> Extend<T> for StableCountedHeap<T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for StableCountedHeap<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut heap = Self::new();
+        heap.extend(iter);
+        heap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableCountedHeap;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_counted_heap() {
+        let mut heap: StableCountedHeap<_> = (0..1000u32).map(|i| i % 5).collect();
+        heap.push_n(7, 3);
+        assert_eq!(heap.len(), 1003);
+        assert_eq!(heap.distinct_len(), 6);
+        assert_eq!(heap.count(&2), 200);
+
+        assert_eq!(heap.pop_all(), Some((7, 3)));
+        assert_eq!(heap.pop(), Some(4));
+        assert_eq!(heap.count(&4), 199);
+
+        let mut expected: Vec<_> = (0..1000u32).map(|i| i % 5).collect();
+        expected.sort_by_key(|i| core::cmp::Reverse(*i));
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(out, expected[1..]);
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_counted_heap_equal_items() {
+        // Compares by the key only, breaking the contract of the heap
+        #[derive(Clone, Debug)]
+        struct Keyed(u32, char);
+
+        impl PartialEq for Keyed {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for Keyed {}
+
+        impl PartialOrd for Keyed {
+            fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Keyed {
+            fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let mut heap: StableCountedHeap<_> = [Keyed(1, 'a'), Keyed(2, 'b'), Keyed(1, 'c')]
+            .into_iter()
+            .collect();
+        assert_eq!(heap.distinct_len(), 2);
+        assert_eq!(heap.pop().map(|i| i.1), Some('b'));
+
+        // Only the first pushed of the equal items is stored
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop()).map(|i| i.1).collect();
+        assert_eq!(out, ['a', 'a']);
+    }
+}
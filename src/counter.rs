@@ -0,0 +1,83 @@
+use crate::item::HeapItem;
+use alloc::vec::Vec;
+use core::{fmt::Debug, hash::Hash, ops::Add};
+
+/// Integer type used for the insertion counter of a `StableBinaryHeap`.
+///
+/// Every item stores one counter, so smaller types save memory while larger
+/// ones need to be renormalized less often. All widths behave the same on
+/// overflow: once the heap's counter reaches `MAX`, all items get renumbered
+/// to `0..len`. This panics if the heap holds more items than the counter type
+/// is able to number
+pub trait Counter: Copy + Ord + Hash + Debug + Add<Output = Self> {
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+
+    /// Converts `n` into a counter if it fits
+    fn from_usize(n: usize) -> Option<Self>;
+}
+
+macro_rules! impl_counter {
+    ($($t:ty),*) => {
+        $(
+            impl Counter for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MAX: Self = <$t>::MAX;
+
+                #[inline]
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                #[inline]
+                fn from_usize(n: usize) -> Option<Self> {
+                    Self::try_from(n).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_counter!(u8, u16, u32, u64, u128, usize);
+
+const TOO_MANY_ITEMS: &str = "too many items for the heap's counter type";
+
+/// Converts `n` into a counter, panicking if `N` is too small
+#[inline]
+pub(crate) fn counter_from_usize<N: Counter>(n: usize) -> N {
+    N::from_usize(n).expect(TOO_MANY_ITEMS)
+}
+
+/// Adds two counters, panicking if `N` is too small. After renormalizing
+/// this only happens if the heap holds more than `N::MAX` items
+#[inline]
+pub(crate) fn counter_add<N: Counter>(a: N, b: N) -> N {
+    a.checked_add(b).expect(TOO_MANY_ITEMS)
+}
+
+/// Renumbers the counters of `items` to `0..len` keeping their relative order
+/// and returns the next counter to use
+pub(crate) fn renormalize<T, N: Counter>(items: &mut [HeapItem<T, N>]) -> N {
+    let mut counters: Vec<N> = items.iter().map(|i| i.counter).collect();
+    let next = renormalize_counters(&mut counters);
+    for (item, counter) in items.iter_mut().zip(counters) {
+        item.counter = counter;
+    }
+    next
+}
+
+/// Same as `renormalize` for counters stored apart from their items
+pub(crate) fn renormalize_counters<N: Counter>(counters: &mut [N]) -> N {
+    let mut order: Vec<usize> = (0..counters.len()).collect();
+    order.sort_unstable_by_key(|pos| counters[*pos]);
+
+    for (counter, pos) in order.into_iter().enumerate() {
+        counters[pos] = counter_from_usize(counter);
+    }
+
+    counter_from_usize(counters.len())
+}
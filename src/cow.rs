@@ -0,0 +1,145 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    counter::Counter,
+    StableBinaryHeap,
+};
+use alloc::sync::Arc;
+use core::{
+    fmt::{self, Debug},
+    ops::Deref,
+};
+
+/// Stable heap that is cloned in O(1) by sharing its items. The items are only
+/// copied when a shared heap gets mutated, which suits snapshots of a heap
+/// that is read more often than it changes. Derefs to the shared
+/// `StableBinaryHeap` for all read only methods
+pub struct StableCowHeap<T, C = MaxComparator, N = usize> {
+    heap: Arc<StableBinaryHeap<T, C, N>>,
+}
+
+impl<T: Ord> StableCowHeap<T> {
+    /// Creates a new empty heap
+    #[inline]
+    pub fn new() -> Self {
+        Self::from(StableBinaryHeap::new())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone, N: Counter> StableCowHeap<T, C, N> {
+    /// Returns the heap for mutation, copying it first if it is shared with
+    /// other clones
+    #[inline]
+    pub fn make_mut(&mut self) -> &mut StableBinaryHeap<T, C, N> {
+        Arc::make_mut(&mut self.heap)
+    }
+
+    #[inline]
+    pub fn push(&mut self, item: T) {
+        self.make_mut().push(item);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        self.make_mut().pop()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        if !self.heap.is_empty() {
+            self.make_mut().clear();
+        }
+    }
+
+    /// Returns the heap, copying it if it is shared with other clones
+    #[inline]
+    pub fn into_inner(self) -> StableBinaryHeap<T, C, N> {
+        Arc::unwrap_or_clone(self.heap)
+    }
+}
+
+impl<T, C, N> StableCowHeap<T, C, N> {
+    /// Returns `true` if the items are shared with other clones, so the next
+    /// mutation copies them
+    #[inline]
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.heap) > 1
+    }
+}
+
+impl<T, C, N> Clone for StableCowHeap<T, C, N> {
+    /// Shares the items with the new heap in O(1)
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            heap: self.heap.clone(),
+        }
+    }
+}
+
+impl<T, C, N> Deref for StableCowHeap<T, C, N> {
+    type Target = StableBinaryHeap<T, C, N>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.heap
+    }
+}
+
+impl<T, C, N> From<StableBinaryHeap<T, C, N>> for StableCowHeap<T, C, N> {
+    #[inline]
+    fn from(heap: StableBinaryHeap<T, C, N>) -> Self {
+        Self {
+            heap: Arc::new(heap),
+        }
+    }
+}
+
+impl<T: Debug, C, N: Debug> Debug for StableCowHeap<T, C, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.heap.fmt(f)
+    }
+}
+
+impl<T: Ord> Default for StableCowHeap<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone, N: Counter> Extend<T> for StableCowHeap<T, C, N> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.make_mut().extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableCowHeap;
+    use crate::compare::KeyComparator;
+    use crate::StableBinaryHeap;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_cow_heap() {
+        let heap: StableBinaryHeap<_, _> =
+            StableBinaryHeap::from_comparator(KeyComparator(|i: &(u32, char)| i.0));
+        let mut heap = StableCowHeap::from(heap);
+        heap.extend([(1, 'a'), (2, 'b'), (1, 'c')]);
+        assert!(!heap.is_shared());
+
+        let snapshot = heap.clone();
+        assert!(heap.is_shared());
+        assert_eq!(heap.pop(), Some((2, 'b')));
+        assert!(!heap.is_shared());
+        heap.push((1, 'd'));
+
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(snapshot.peek(), Some(&(2, 'b')));
+        let out: Vec<_> = heap.into_inner().into_iter_sorted().map(|i| i.1).collect();
+        assert_eq!(out, ['a', 'c', 'd']);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    counter::{self, Counter},
+    item::HeapItem,
+};
+use alloc::vec::Vec;
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+    mem,
+};
+
+/// Stable heap in which every node has `D` children instead of two. A larger
+/// arity makes the tree flatter, so pushes compare fewer items while each level
+/// of a pop compares more. Equal items are returned in inserted order
+#[derive(Clone)]
+pub struct StableDaryHeap<T, const D: usize, C = MaxComparator, N = usize> {
+    data: Vec<HeapItem<T, N>>,
+    counter: N,
+    cmp: C,
+}
+
+impl<T: Ord, const D: usize> StableDaryHeap<T, D> {
+    /// Creates a new stable d-ary heap
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_comparator(MaxComparator)
+    }
+
+    /// Creates a new stable d-ary heap with a given capacity
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_comparator(capacity, MaxComparator)
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>, N: Counter> StableDaryHeap<T, D, C, N> {
+    /// Creates a new stable d-ary heap ordered by `cmp`
+    #[inline]
+    pub fn from_comparator(cmp: C) -> Self {
+        Self::with_capacity_and_comparator(0, cmp)
+    }
+
+    /// Creates a new stable d-ary heap with a given capacity ordered by `cmp`
+    #[inline]
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> Self {
+        const { assert!(D >= 2, "a d-ary heap needs at least two children per item") };
+        Self {
+            data: Vec::with_capacity(capacity),
+            counter: N::ZERO,
+            cmp,
+        }
+    }
+
+    /// Pushes a new element on the heap
+    pub fn push(&mut self, item: T) {
+        if self.counter == N::MAX {
+            self.counter = counter::renormalize(&mut self.data);
+        }
+
+        let item = HeapItem::new(item, self.counter);
+        self.counter = counter::counter_add(self.counter, N::ONE);
+
+        self.data.push(item);
+        self.sift_up(self.data.len() - 1);
+    }
+
+    /// Removes the greatest item and returns it. Of equal items the oldest one
+    /// is returned
+    pub fn pop(&mut self) -> Option<T> {
+        let item = self.data.pop()?;
+        if self.data.is_empty() {
+            return Some(item.into_inner());
+        }
+
+        let item = mem::replace(&mut self.data[0], item);
+        self.sift_down(0);
+        Some(item.into_inner())
+    }
+
+    /// Returns the greatest item
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first().map(|i| i.inner())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.counter = N::ZERO;
+    }
+
+    /// Returns an iterator over all items in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.data.iter().map(|i| i.inner())
+    }
+
+    /// Returns all items in arbitrary order
+    #[inline]
+    pub fn into_vec(self) -> Vec<T> {
+        self.data.into_iter().map(|i| i.into_inner()).collect()
+    }
+
+    /// Returns all items sorted in popping order, greatest first
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut out = Vec::with_capacity(self.len());
+        while let Some(item) = self.pop() {
+            out.push(item);
+        }
+        out
+    }
+
+    /// Get the stable d-ary heap's counter.
+    #[inline]
+    pub fn counter(&self) -> N {
+        self.counter
+    }
+
+    /// Compares the items at the positions `a` and `b`
+    #[inline]
+    fn cmp_at(&self, a: usize, b: usize) -> Ordering {
+        self.cmp.compare_items(&self.data[a], &self.data[b])
+    }
+
+    /// Moves the item at `pos` up until its parent is greater
+    fn sift_up(&mut self, mut pos: usize) {
+        while pos > 0 {
+            let parent = (pos - 1) / D;
+            if self.cmp_at(pos, parent) != Ordering::Greater {
+                break;
+            }
+
+            self.data.swap(pos, parent);
+            pos = parent;
+        }
+    }
+
+    /// Moves the item at `pos` down until all of its children are smaller
+    fn sift_down(&mut self, mut pos: usize) {
+        let len = self.len();
+        loop {
+            let first = D * pos + 1;
+            if first >= len {
+                break;
+            }
+
+            let mut child = first;
+            for i in first + 1..(first + D).min(len) {
+                if self.cmp_at(i, child) == Ordering::Greater {
+                    child = i;
+                }
+            }
+
+            if self.cmp_at(child, pos) != Ordering::Greater {
+                break;
+            }
+
+            self.data.swap(pos, child);
+            pos = child;
+        }
+    }
+}
+
+impl<T: Debug, const D: usize, C, N: Debug> Debug for StableDaryHeap<T, D, C, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableDaryHeap")
+            .field("data", &self.data)
+            .field("counter", &self.counter)
+            .finish()
+    }
+}
+
+impl<T, const D: usize, C, N> Default for StableDaryHeap<T, D, C, N>
+where
+    C: Compare<T> + Default,
+    N: Counter,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::from_comparator(C::default())
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>, N: Counter> Extend<T> for StableDaryHeap<T, D, C, N> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for i in iter {
+            self.push(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableDaryHeap;
+    use crate::{
+        compare::KeyComparator,
+        test_util::{keyed_items, sorted_desc},
+    };
+
+    fn check_arity<const D: usize>() {
+        let items = keyed_items(1000, 23);
+
+        let expected = sorted_desc(items.iter().copied());
+
+        let mut heap: StableDaryHeap<_, D, _> =
+            StableDaryHeap::from_comparator(KeyComparator(|i: &(u32, u32)| i.0));
+        heap.extend(items);
+        assert_eq!(heap.peek(), expected.first());
+        assert_eq!(heap.into_sorted_vec(), expected);
+    }
+
+    #[test]
+    fn test_dary_heap() {
+        check_arity::<2>();
+        check_arity::<3>();
+        check_arity::<4>();
+        check_arity::<8>();
+
+        let mut heap = StableDaryHeap::<_, 4>::new();
+        assert_eq!(heap.pop(), None);
+        heap.push(1);
+        assert_eq!(heap.pop(), Some(1));
+        assert!(heap.is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    handle::Handle,
+    StableLazyHeap,
+};
+use core::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    hash::Hash,
+};
+use std::collections::HashMap;
+
+/// Stable heap holding at most one item per identity, as returned by `F`.
+/// Pushing an item whose identity is already present is a no-op, while
+/// `push_or_refresh` replaces the present item instead. Useful for search
+/// frontiers that would otherwise need a separate set of queued items
+pub struct StableDedupHeap<T, K, F, C = MaxComparator> {
+    heap: StableLazyHeap<T, C>,
+    /// Handles of the queued items by their identity
+    queued: HashMap<K, Handle>,
+    identity: F,
+}
+
+impl<T: Ord, K: Hash + Eq, F: Fn(&T) -> K> StableDedupHeap<T, K, F> {
+    /// Creates a new empty heap identifying items with `identity`
+    #[inline]
+    pub fn new(identity: F) -> Self {
+        Self::from_comparator(identity, MaxComparator)
+    }
+}
+
+impl<T, K, F, C> StableDedupHeap<T, K, F, C>
+where
+    K: Hash + Eq,
+    F: Fn(&T) -> K,
+    C: Compare<T>,
+{
+    /// Creates a new empty heap identifying items with `identity` and
+    /// ordering them by `cmp`
+    #[inline]
+    pub fn from_comparator(identity: F, cmp: C) -> Self {
+        Self {
+            heap: StableLazyHeap::from_comparator(cmp),
+            queued: HashMap::new(),
+            identity,
+        }
+    }
+
+    /// Pushes `item` unless an item with the same identity is already queued.
+    /// Returns `false` if nothing was pushed
+    pub fn push(&mut self, item: T) -> bool {
+        let id = (self.identity)(&item);
+        if self.queued.contains_key(&id) {
+            return false;
+        }
+
+        let handle = self.heap.push(item);
+        self.queued.insert(id, handle);
+        true
+    }
+
+    /// Pushes `item`, replacing the item with the same identity if there is
+    /// one. The new item is ordered as if the replaced one had been popped
+    /// before pushing it. Returns `true` if an item was replaced
+    pub fn push_or_refresh(&mut self, item: T) -> bool {
+        let id = (self.identity)(&item);
+        let replaced = match self.queued.get(&id) {
+            Some(handle) => self.heap.cancel(*handle),
+            None => false,
+        };
+
+        let handle = self.heap.push(item);
+        self.queued.insert(id, handle);
+        replaced
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let item = self.heap.pop()?;
+        self.queued.remove(&(self.identity)(&item));
+        Some(item)
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.peek()
+    }
+
+    /// Returns `true` if an item with the identity `id` is queued
+    #[inline]
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.queued.contains_key(id)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.queued.clear();
+    }
+
+    /// Returns an iterator over all items in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.heap.iter()
+    }
+}
+
+impl<T: Debug, K, F, C: Compare<T>> Debug for StableDedupHeap<T, K, F, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.heap.iter()).finish()
+    }
+}
+
+impl<T, K, F, C> Extend<T> for StableDedupHeap<T, K, F, C>
+where
+    K: Hash + Eq,
+    F: Fn(&T) -> K,
+    C: Compare<T>,
+{
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableDedupHeap;
+    use crate::compare::KeyComparator;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_dedup_heap() {
+        let mut heap = StableDedupHeap::from_comparator(
+            |i: &(char, u32)| i.0,
+            KeyComparator(|i: &(char, u32)| i.1),
+        );
+        assert!(heap.push(('a', 1)));
+        assert!(heap.push(('b', 2)));
+        assert!(!heap.push(('a', 5)));
+        heap.extend([('c', 1), ('b', 0)]);
+        assert_eq!(heap.len(), 3);
+        assert_eq!(heap.peek(), Some(&('b', 2)));
+
+        assert!(heap.push_or_refresh(('b', 1)));
+        assert!(!heap.push_or_refresh(('d', 3)));
+        assert!(heap.contains(&'b'));
+
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(out, [('d', 3), ('a', 1), ('c', 1), ('b', 1)]);
+        assert!(!heap.contains(&'b'));
+        assert!(heap.push(('b', 0)));
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{compare::Compare, StableBinaryHeap};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+    iter::FusedIterator,
+    marker::PhantomData,
+};
+
+/// Queue of items that become due at a deadline, usually a
+/// `std::time::Instant`. Items with the same deadline are returned in the
+/// order they were pushed.
+///
+/// The items are stored in the backend `B`, a stable binary heap by default.
+/// For millions of timers with integer deadlines a `TimingWheel` can be
+/// used instead, see `DelayQueue::with_backend`
+#[derive(Clone)]
+pub struct DelayQueue<T, I, B = HeapBackend<T, I>> {
+    backend: B,
+    marker: PhantomData<fn() -> (T, I)>,
+}
+
+/// Storage of the items of a `DelayQueue`
+pub trait DelayBackend<T, I> {
+    fn push(&mut self, item: T, deadline: I);
+
+    /// Removes and returns the item with the earliest deadline if it is due at
+    /// `now`
+    fn pop_next_due(&mut self, now: &I) -> Option<T>;
+
+    /// Returns the item with the earliest deadline together with its deadline
+    fn peek(&self) -> Option<(&T, &I)>;
+
+    fn len(&self) -> usize;
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn clear(&mut self);
+
+    /// Returns an iterator over all items and their deadlines in arbitrary
+    /// order
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a T, &'a I)>
+    where
+        T: 'a,
+        I: 'a;
+}
+
+/// Default backend of a `DelayQueue`, a stable binary heap ordered by deadline
+#[derive(Clone)]
+pub struct HeapBackend<T, I> {
+    heap: StableBinaryHeap<Delayed<T, I>, DeadlineComparator>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Delayed<T, I> {
+    pub(crate) item: T,
+    pub(crate) deadline: I,
+}
+
+/// Orders delayed items earliest deadline first
+#[derive(Clone, Copy, Default)]
+pub(crate) struct DeadlineComparator;
+
+impl<T, I: Ord> Compare<Delayed<T, I>> for DeadlineComparator {
+    #[inline]
+    fn compare(&self, a: &Delayed<T, I>, b: &Delayed<T, I>) -> Ordering {
+        b.deadline.cmp(&a.deadline)
+    }
+}
+
+impl<T, I: Ord> HeapBackend<T, I> {
+    /// Creates a new empty backend
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            heap: StableBinaryHeap::from_comparator(DeadlineComparator),
+        }
+    }
+}
+
+impl<T, I: Ord> DelayBackend<T, I> for HeapBackend<T, I> {
+    #[inline]
+    fn push(&mut self, item: T, deadline: I) {
+        self.heap.push(Delayed { item, deadline });
+    }
+
+    fn pop_next_due(&mut self, now: &I) -> Option<T> {
+        if self.heap.peek()?.deadline > *now {
+            return None;
+        }
+        self.heap.pop().map(|i| i.item)
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<(&T, &I)> {
+        self.heap.peek().map(|i| (&i.item, &i.deadline))
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.heap.clear();
+    }
+
+    #[inline]
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a T, &'a I)>
+    where
+        T: 'a,
+        I: 'a,
+    {
+        self.heap.iter().map(|i| (&i.item, &i.deadline))
+    }
+}
+
+impl<T, I: Ord> Default for HeapBackend<T, I> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, I: Ord> DelayQueue<T, I> {
+    /// Creates a new empty delay queue
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_backend(HeapBackend::new())
+    }
+}
+
+impl<T, I, B: DelayBackend<T, I>> DelayQueue<T, I, B> {
+    /// Creates a new empty delay queue storing its items in `backend`
+    #[inline]
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            marker: PhantomData,
+        }
+    }
+
+    /// Pushes `item` to become due at `deadline`
+    #[inline]
+    pub fn push(&mut self, item: T, deadline: I) {
+        self.backend.push(item, deadline);
+    }
+
+    /// Removes and returns the item with the earliest deadline if it is due at
+    /// `now`
+    #[inline]
+    pub fn pop_next_due(&mut self, now: &I) -> Option<T> {
+        self.backend.pop_next_due(now)
+    }
+
+    /// Returns an iterator removing all items due at `now`, earliest deadline
+    /// first. Items not consumed from the iterator stay in the queue
+    #[inline]
+    pub fn pop_due<'a>(&'a mut self, now: &'a I) -> PopDue<'a, T, I, B> {
+        PopDue { queue: self, now }
+    }
+
+    /// Returns the item with the earliest deadline together with its deadline
+    #[inline]
+    pub fn peek(&self) -> Option<(&T, &I)> {
+        self.backend.peek()
+    }
+
+    /// Returns the earliest deadline
+    #[inline]
+    pub fn next_deadline(&self) -> Option<&I> {
+        self.backend.peek().map(|(_, deadline)| deadline)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.backend.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.backend.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.backend.clear();
+    }
+}
+
+impl<T: Debug, I: Debug, B: DelayBackend<T, I>> Debug for DelayQueue<T, I, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.backend.iter().map(|(item, deadline)| (deadline, item)))
+            .finish()
+    }
+}
+
+impl<T, I, B: DelayBackend<T, I> + Default> Default for DelayQueue<T, I, B> {
+    #[inline]
+    fn default() -> Self {
+        Self::with_backend(B::default())
+    }
+}
+
+impl<T, I, B: DelayBackend<T, I>> Extend<(T, I)> for DelayQueue<T, I, B> {
+    #[inline]
+    fn extend<It: IntoIterator<Item = (T, I)>>(&mut self, iter: It) {
+        for (item, deadline) in iter {
+            self.push(item, deadline);
+        }
+    }
+}
+
+/// Iterator returned by [`DelayQueue::pop_due`]
+pub struct PopDue<'a, T, I, B = HeapBackend<T, I>> {
+    queue: &'a mut DelayQueue<T, I, B>,
+    now: &'a I,
+}
+
+impl<T, I, B: DelayBackend<T, I>> Iterator for PopDue<'_, T, I, B> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.queue.pop_next_due(self.now)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.queue.len()))
+    }
+}
+
+impl<T, I, B: DelayBackend<T, I>> FusedIterator for PopDue<'_, T, I, B> {}
+
+#[cfg(test)]
+mod tests {
+    use super::DelayQueue;
+    use alloc::vec::Vec;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_delay_queue() {
+        let mut queue = DelayQueue::new();
+        queue.extend([("a", 3), ("b", 1), ("c", 3), ("d", 2), ("e", 1), ("f", 5)]);
+        assert_eq!(queue.next_deadline(), Some(&1));
+
+        assert_eq!(queue.pop_due(&0).next(), None);
+        assert_eq!(queue.pop_due(&1).collect::<Vec<_>>(), ["b", "e"]);
+        assert_eq!(queue.pop_due(&4).collect::<Vec<_>>(), ["d", "a", "c"]);
+        assert_eq!(queue.peek(), Some((&"f", &5)));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_delay_queue_instant() {
+        let now = Instant::now();
+        let mut queue = DelayQueue::new();
+        for i in 0..10 {
+            queue.push(i, now + Duration::from_secs(i % 3));
+        }
+
+        let due: Vec<_> = queue.pop_due(&(now + Duration::from_secs(1))).collect();
+        assert_eq!(due, [0, 3, 6, 9, 1, 4, 7]);
+        assert_eq!(queue.pop_next_due(&now), None);
+    }
+}
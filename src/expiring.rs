@@ -0,0 +1,177 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    StableBinaryHeap,
+};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+};
+
+/// Stable heap whose items can expire at a timestamp `I`, usually a
+/// `std::time::Instant`. Expired items are skipped when popping and can be
+/// removed in bulk with `purge_expired`. An item is expired once `now` has
+/// reached its expiry
+#[derive(Clone)]
+pub struct StableExpiringHeap<T, I, C = MaxComparator> {
+    heap: StableBinaryHeap<Expiring<T, I>, ItemComparator<C>>,
+}
+
+#[derive(Clone, Debug)]
+struct Expiring<T, I> {
+    item: T,
+    expiry: Option<I>,
+}
+
+impl<T, I: Ord> Expiring<T, I> {
+    #[inline]
+    fn is_expired(&self, now: &I) -> bool {
+        self.expiry.as_ref().is_some_and(|expiry| expiry <= now)
+    }
+}
+
+/// Orders the items by `C`, ignoring their expiry
+#[derive(Clone, Copy)]
+struct ItemComparator<C>(C);
+
+impl<T, I, C: Compare<T>> Compare<Expiring<T, I>> for ItemComparator<C> {
+    #[inline]
+    fn compare(&self, a: &Expiring<T, I>, b: &Expiring<T, I>) -> Ordering {
+        self.0.compare(&a.item, &b.item)
+    }
+
+    #[inline]
+    fn compare_parts<N: Ord>(
+        &self,
+        a: &Expiring<T, I>,
+        a_counter: &N,
+        b: &Expiring<T, I>,
+        b_counter: &N,
+    ) -> Ordering {
+        self.0.compare_parts(&a.item, a_counter, &b.item, b_counter)
+    }
+}
+
+impl<T: Ord, I: Ord> StableExpiringHeap<T, I> {
+    /// Creates a new empty heap
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_comparator(MaxComparator)
+    }
+}
+
+impl<T, I: Ord, C: Compare<T>> StableExpiringHeap<T, I, C> {
+    /// Creates a new empty heap ordered by `cmp`
+    #[inline]
+    pub fn from_comparator(cmp: C) -> Self {
+        Self {
+            heap: StableBinaryHeap::from_comparator(ItemComparator(cmp)),
+        }
+    }
+
+    /// Pushes an item that never expires
+    #[inline]
+    pub fn push(&mut self, item: T) {
+        self.heap.push(Expiring { item, expiry: None });
+    }
+
+    /// Pushes an item that expires at `expiry`
+    #[inline]
+    pub fn push_with_expiry(&mut self, item: T, expiry: I) {
+        self.heap.push(Expiring {
+            item,
+            expiry: Some(expiry),
+        });
+    }
+
+    /// Removes the greatest item not expired at `now` and returns it. Expired
+    /// items on top of it are dropped
+    pub fn pop(&mut self, now: &I) -> Option<T> {
+        self.skip_expired(now);
+        self.heap.pop().map(|i| i.item)
+    }
+
+    /// Returns the greatest item not expired at `now`. Expired items on top of
+    /// it are dropped
+    pub fn peek(&mut self, now: &I) -> Option<&T> {
+        self.skip_expired(now);
+        self.heap.peek().map(|i| &i.item)
+    }
+
+    /// Removes all items expired at `now` in O(n) and returns how many were
+    /// removed
+    pub fn purge_expired(&mut self, now: &I) -> usize {
+        let len = self.heap.len();
+        self.heap.retain(|i| !i.is_expired(now));
+        len - self.heap.len()
+    }
+
+    /// Returns the amount of items, including the expired ones that haven't
+    /// been dropped yet
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.heap.clear();
+    }
+
+    /// Returns an iterator over all items and their expiry in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&T, Option<&I>)> {
+        self.heap.iter().map(|i| (&i.item, i.expiry.as_ref()))
+    }
+
+    /// Drops the top items while they are expired
+    fn skip_expired(&mut self, now: &I) {
+        while self.heap.peek().is_some_and(|i| i.is_expired(now)) {
+            self.heap.pop();
+        }
+    }
+}
+
+impl<T: Debug, I: Debug, C> Debug for StableExpiringHeap<T, I, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StableExpiringHeap")
+            .field(&self.heap)
+            .finish()
+    }
+}
+
+impl<T: Ord, I: Ord> Default for StableExpiringHeap<T, I> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableExpiringHeap;
+    use crate::compare::KeyComparator;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_expiring_heap() {
+        let mut heap: StableExpiringHeap<_, u32, _> =
+            StableExpiringHeap::from_comparator(KeyComparator(|i: &(u32, char)| i.0));
+        heap.push_with_expiry((3, 'a'), 10);
+        heap.push((1, 'b'));
+        heap.push_with_expiry((3, 'c'), 20);
+        heap.push_with_expiry((2, 'd'), 5);
+        heap.push_with_expiry((1, 'e'), 30);
+
+        assert_eq!(heap.peek(&10), Some(&(3, 'c')));
+        assert_eq!(heap.len(), 4);
+        assert_eq!(heap.purge_expired(&20), 2);
+
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop(&20)).map(|i| i.1).collect();
+        assert_eq!(out, ['b', 'e']);
+    }
+}
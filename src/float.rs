@@ -0,0 +1,70 @@
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+macro_rules! impl_total_float {
+    ($($(#[$doc:meta])* $name:ident($t:ty);)*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Clone, Copy, Debug, Default)]
+            pub struct $name(pub $t);
+
+            impl PartialEq for $name {
+                #[inline]
+                fn eq(&self, other: &Self) -> bool {
+                    self.cmp(other) == Ordering::Equal
+                }
+            }
+
+            impl Eq for $name {}
+
+            impl PartialOrd for $name {
+                #[inline]
+                fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                    Some(self.cmp(other))
+                }
+            }
+
+            impl Ord for $name {
+                #[inline]
+                fn cmp(&self, other: &Self) -> Ordering {
+                    self.0.total_cmp(&other.0)
+                }
+            }
+
+            impl Hash for $name {
+                #[inline]
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    self.0.to_bits().hash(state);
+                }
+            }
+
+            impl From<$t> for $name {
+                #[inline]
+                fn from(f: $t) -> Self {
+                    Self(f)
+                }
+            }
+
+            impl From<$name> for $t {
+                #[inline]
+                fn from(f: $name) -> Self {
+                    f.0
+                }
+            }
+        )*
+    };
+}
+
+impl_total_float! {
+    /// `f32` ordered by `f32::total_cmp`, so it can be used as a priority, e.g.
+    /// with `StableBinaryHeap::new_by_key(|i| TotalF32(i.score))`. Positive NaN
+    /// is greater and negative NaN smaller than all other values
+    TotalF32(f32);
+
+    /// `f64` ordered by `f64::total_cmp`, so it can be used as a priority, e.g.
+    /// with `StableBinaryHeap::new_by_key(|i| TotalF64(i.score))`. Positive NaN
+    /// is greater and negative NaN smaller than all other values
+    TotalF64(f64);
+}
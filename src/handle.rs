@@ -0,0 +1,27 @@
+/// Refers to an item pushed with `StableBinaryHeap::push_with_handle`.
+///
+/// A handle stores the insertion sequence number (the counter) of its item.
+/// Handles issued before the heap's counter got reset by `clear` or `drain`,
+/// or renumbered, no longer refer to any item. The same goes for handles of a
+/// heap that got appended to another one. Finding the item of a handle takes
+/// O(n), see `StablePriorityQueue` for O(log n) priority changes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle<N = usize> {
+    pub(crate) seq: N,
+    /// Amount of times the issuing heap renumbered its items before the
+    /// handle was created
+    pub(crate) epoch: u32,
+}
+
+impl<N: Copy> Handle<N> {
+    #[inline]
+    pub(crate) fn new(seq: N, epoch: u32) -> Self {
+        Self { seq, epoch }
+    }
+
+    /// Get the insertion sequence number of the handle's item.
+    #[inline]
+    pub fn seq(&self) -> N {
+        self.seq
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{
+    compare::{Compare, MaxComparator, MinComparator},
+    StableBinaryHeap,
+};
+use alloc::vec::Vec;
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+};
+
+/// Stable heap for large items. The heap only holds the key of each item and
+/// the index of the slot it's stored in, so sifting moves small entries while
+/// the items themselves stay in place. The key is extracted once on push with
+/// `F` and ordered by `C`
+pub struct StableIndexedHeap<T, K, F, C = MaxComparator> {
+    heap: StableBinaryHeap<Entry<K>, EntryComparator<C>>,
+    slots: Vec<Option<T>>,
+    /// Indices of the empty slots in `slots`
+    free: Vec<usize>,
+    key: F,
+}
+
+struct Entry<K> {
+    key: K,
+    slot: usize,
+}
+
+/// Orders entries by their key only
+struct EntryComparator<C>(C);
+
+impl<K, C: Compare<K>> Compare<Entry<K>> for EntryComparator<C> {
+    #[inline]
+    fn compare(&self, a: &Entry<K>, b: &Entry<K>) -> Ordering {
+        self.0.compare(&a.key, &b.key)
+    }
+
+    #[inline]
+    fn compare_parts<N: Ord>(
+        &self,
+        a: &Entry<K>,
+        a_counter: &N,
+        b: &Entry<K>,
+        b_counter: &N,
+    ) -> Ordering {
+        self.0.compare_parts(&a.key, a_counter, &b.key, b_counter)
+    }
+}
+
+impl<T, K: Ord, F: Fn(&T) -> K> StableIndexedHeap<T, K, F> {
+    /// Creates a new empty heap popping the item with the greatest key first
+    #[inline]
+    pub fn new(key: F) -> Self {
+        Self::from_comparator(key, MaxComparator)
+    }
+}
+
+impl<T, K: Ord, F: Fn(&T) -> K> StableIndexedHeap<T, K, F, MinComparator> {
+    /// Creates a new empty heap popping the item with the smallest key first
+    #[inline]
+    pub fn new_min(key: F) -> Self {
+        Self::from_comparator(key, MinComparator)
+    }
+}
+
+impl<T, K, F: Fn(&T) -> K, C: Compare<K>> StableIndexedHeap<T, K, F, C> {
+    /// Creates a new empty heap ordering the keys by `cmp`
+    #[inline]
+    pub fn from_comparator(key: F, cmp: C) -> Self {
+        Self {
+            heap: StableBinaryHeap::from_comparator(EntryComparator(cmp)),
+            slots: Vec::new(),
+            free: Vec::new(),
+            key,
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        let key = (self.key)(&item);
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(item);
+                slot
+            }
+            None => {
+                self.slots.push(Some(item));
+                self.slots.len() - 1
+            }
+        };
+        self.heap.push(Entry { key, slot });
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let entry = self.heap.pop()?;
+        let item = self.slots[entry.slot].take();
+
+        if self.heap.is_empty() {
+            self.slots.clear();
+            self.free.clear();
+        } else {
+            self.free.push(entry.slot);
+        }
+        item
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.heap
+            .peek()
+            .and_then(|entry| self.slots[entry.slot].as_ref())
+    }
+
+    /// Returns the key of the greatest item
+    #[inline]
+    pub fn peek_key(&self) -> Option<&K> {
+        self.heap.peek().map(|entry| &entry.key)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.slots.clear();
+        self.free.clear();
+    }
+
+    /// Returns an iterator visiting all items in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().flatten()
+    }
+}
+
+impl<T, K, F: Fn(&T) -> K, C: Compare<K>> Extend<T> for StableIndexedHeap<T, K, F, C> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Debug, K, F, C> Debug for StableIndexedHeap<T, K, F, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.slots.iter().flatten()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableIndexedHeap;
+    use crate::test_util::scatter;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_indexed_heap() {
+        let mut heap = StableIndexedHeap::new(|i: &(u32, [u8; 256])| i.0 % 7);
+        let mut expected = Vec::new();
+        for i in 0..100u32 {
+            let key = scatter(i, 100);
+            heap.push((key, [i as u8; 256]));
+            expected.push((key, i as u8));
+        }
+        expected.sort_by_key(|i| std::cmp::Reverse(i.0 % 7));
+
+        // Popping some items frees slots that are reused afterwards
+        let mut out = Vec::new();
+        for _ in 0..30 {
+            out.push(heap.pop().unwrap());
+        }
+        heap.push((0, [200; 256]));
+        assert_eq!(heap.len(), 71);
+        while let Some(i) = heap.pop() {
+            out.push(i);
+        }
+        expected.push((0, 200));
+
+        let out: Vec<_> = out.into_iter().map(|i| (i.0, i.1[0])).collect();
+        assert_eq!(out, expected);
+        assert!(heap.peek().is_none());
+    }
+
+    #[test]
+    fn test_indexed_min_heap() {
+        let mut heap = StableIndexedHeap::new_min(|i: &(u32, char)| i.0);
+        heap.extend([(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')]);
+        assert_eq!(heap.peek_key(), Some(&1));
+
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop()).map(|i| i.1).collect();
+        assert_eq!(out, ['b', 'd', 'a', 'c']);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{buffer::Allocator, compare::Compare, counter::Counter, StableBinaryHeap};
+use alloc::vec::Vec;
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug, Display},
+};
+
+/// Inconsistency found by `StableBinaryHeap::check_integrity`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegrityError<N> {
+    /// The item at `pos` is greater than its parent
+    HeapOrder { pos: usize, parent: usize },
+    /// The item at `pos` has a counter that isn't below the heap's counter
+    CounterOutOfRange { pos: usize, counter: N },
+    /// More than one item has the counter `counter`
+    DuplicateCounter { counter: N },
+}
+
+impl<N: Debug> Display for IntegrityError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HeapOrder { pos, parent } => {
+                write!(f, "item at {pos} is greater than its parent at {parent}")
+            }
+            Self::CounterOutOfRange { pos, counter } => {
+                write!(
+                    f,
+                    "item at {pos} has the counter {counter:?} which hasn't been assigned yet"
+                )
+            }
+            Self::DuplicateCounter { counter } => {
+                write!(f, "counter {counter:?} is used by multiple items")
+            }
+        }
+    }
+}
+
+impl<N: Debug> core::error::Error for IntegrityError<N> {}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> StableBinaryHeap<T, C, N, A> {
+    /// Verifies that every item is smaller than its parent and that all
+    /// counters are unique and below the heap's counter. Useful to detect
+    /// items that were changed in a way that alters their order without the
+    /// heap knowing about it, which `rebuild` fixes as long as the counters are
+    /// intact
+    pub fn check_integrity(&self) -> Result<(), IntegrityError<N>> {
+        for pos in 1..self.len() {
+            let parent = (pos - 1) / 2;
+            if self.cmp_at(pos, parent) == Ordering::Greater {
+                return Err(IntegrityError::HeapOrder { pos, parent });
+            }
+        }
+
+        self.check_counters()
+    }
+
+    /// Verifies that all counters are unique and below the heap's counter
+    pub(crate) fn check_counters(&self) -> Result<(), IntegrityError<N>> {
+        for (pos, counter) in self.counters.iter().enumerate() {
+            if *counter >= self.counter {
+                let counter = *counter;
+                return Err(IntegrityError::CounterOutOfRange { pos, counter });
+            }
+        }
+
+        let mut counters: Vec<N> = self.counters.to_vec();
+        counters.sort_unstable();
+        match counters.windows(2).find(|w| w[0] == w[1]) {
+            Some(w) => Err(IntegrityError::DuplicateCounter { counter: w[0] }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the item at `pos` against its parent and children and its
+    /// counter against the heap's counter in debug builds. Unlike
+    /// `check_integrity` this takes O(1)
+    #[inline]
+    pub(crate) fn debug_check_at(&self, pos: usize) {
+        #[cfg(debug_assertions)]
+        {
+            if pos > 0 {
+                let parent = (pos - 1) / 2;
+                debug_assert!(
+                    self.cmp_at(pos, parent) != Ordering::Greater,
+                    "corrupted heap: {}",
+                    IntegrityError::<N>::HeapOrder { pos, parent }
+                );
+            }
+            for child in [2 * pos + 1, 2 * pos + 2] {
+                debug_assert!(
+                    child >= self.len() || self.cmp_at(child, pos) != Ordering::Greater,
+                    "corrupted heap: {}",
+                    IntegrityError::<N>::HeapOrder {
+                        pos: child,
+                        parent: pos
+                    }
+                );
+            }
+            debug_assert!(
+                self.counters.get(pos).is_none_or(|c| *c < self.counter),
+                "corrupted heap: {}",
+                IntegrityError::CounterOutOfRange {
+                    pos,
+                    counter: self.counters[pos]
+                }
+            );
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = pos;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntegrityError;
+    use crate::StableBinaryHeap;
+    use alloc::string::ToString;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_check_integrity() {
+        let mut heap: StableBinaryHeap<_> = (0..100).map(|i| i % 7).collect();
+        assert_eq!(heap.check_integrity(), Ok(()));
+
+        heap.items[50] = 10;
+        let err = heap.check_integrity().unwrap_err();
+        assert_eq!(
+            err,
+            IntegrityError::HeapOrder {
+                pos: 50,
+                parent: 24
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "item at 50 is greater than its parent at 24"
+        );
+        heap.rebuild();
+
+        let counter = heap.counters[98];
+        heap.counters[98] = heap.counters[99];
+        assert!(matches!(
+            heap.check_integrity(),
+            Err(IntegrityError::DuplicateCounter { .. })
+        ));
+
+        heap.counters[98] = counter;
+        // A newer counter keeps a leaf below its parent
+        heap.counters[99] = 100;
+        assert_eq!(
+            heap.check_integrity(),
+            Err(IntegrityError::CounterOutOfRange {
+                pos: 99,
+                counter: 100
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_integrity_interior_mutability() {
+        let mut heap = StableBinaryHeap::new_by_key(|i: &Cell<u32>| i.get());
+        heap.extend((0..10).map(Cell::new));
+
+        heap.iter().last().unwrap().set(100);
+        assert!(heap.check_integrity().is_err());
+    }
+}
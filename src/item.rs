@@ -1,16 +1,23 @@
-use std::{
+use crate::compare::{Compare, MaxComparator};
+use core::{
     cmp::Ordering,
     ops::{Deref, DerefMut},
 };
 
-pub struct HeapItem<T> {
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct HeapItem<T, N = usize> {
     pub inner: T,
-    pub counter: usize,
+    pub counter: N,
 }
 
-impl<T: Ord> HeapItem<T> {
+impl<T, N> HeapItem<T, N> {
     #[inline]
-    pub fn new(inner: T, pos: usize) -> Self {
+    pub fn new(inner: T, pos: N) -> Self {
         HeapItem {
             inner,
             counter: pos,
@@ -33,20 +40,31 @@ impl<T: Ord> HeapItem<T> {
         self.inner
     }
 
+    /// Compares two heap items using `cmp` for their inner values. Equal values
+    /// are ordered by their counter so that the older item is the greater one
+    #[inline]
+    pub fn cmp_by<C: Compare<T> + ?Sized>(&self, other: &Self, cmp: &C) -> Ordering
+    where
+        N: Ord,
+    {
+        cmp.compare(&self.inner, &other.inner)
+            .then_with(|| self.counter.cmp(&other.counter).reverse())
+    }
+
     /// Get a mutable reference to the heap item's counter.
-    pub fn counter_mut(&mut self) -> &mut usize {
+    pub fn counter_mut(&mut self) -> &mut N {
         &mut self.counter
     }
 }
 
-impl<T> AsRef<T> for HeapItem<T> {
+impl<T, N> AsRef<T> for HeapItem<T, N> {
     #[inline]
     fn as_ref(&self) -> &T {
         &self.inner
     }
 }
 
-impl<T> Deref for HeapItem<T> {
+impl<T, N> Deref for HeapItem<T, N> {
     type Target = T;
 
     #[inline]
@@ -55,37 +73,32 @@ impl<T> Deref for HeapItem<T> {
     }
 }
 
-impl<T> DerefMut for HeapItem<T> {
+impl<T, N> DerefMut for HeapItem<T, N> {
     #[inline]
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.inner
     }
 }
 
-impl<T: Ord + PartialEq> PartialEq for HeapItem<T> {
+impl<T: Ord + PartialEq, N: Ord> PartialEq for HeapItem<T, N> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
         self.counter == other.counter && self.inner == other.inner
     }
 }
 
-impl<T: Ord + PartialEq> Eq for HeapItem<T> {}
+impl<T: Ord + PartialEq, N: Ord> Eq for HeapItem<T, N> {}
 
-impl<T: Ord + PartialEq> PartialOrd for HeapItem<T> {
+impl<T: Ord + PartialEq, N: Ord> PartialOrd for HeapItem<T, N> {
     #[inline]
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let cmp = self.inner.cmp(&other.inner);
-        if cmp == Ordering::Equal {
-            return Some(self.counter.cmp(&other.counter).reverse());
-        }
-
-        Some(cmp)
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-impl<T: Ord + PartialEq> Ord for HeapItem<T> {
+impl<T: Ord + PartialEq, N: Ord> Ord for HeapItem<T, N> {
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        self.cmp_by(other, &MaxComparator)
     }
 }
@@ -0,0 +1,118 @@
+use crate::{compare::Compare, StableBinaryHeap};
+use alloc::vec::Vec;
+use core::{cmp::Ordering, iter::FusedIterator};
+
+/// Merges already sorted iterators into one sorted iterator, smallest first.
+/// Equal items are returned in the order of the iterators they come from
+pub fn kmerge_stable<I>(iters: I) -> KMergeStable<IterOf<I>, OrdFn<ItemOf<I>>>
+where
+    I: IntoIterator,
+    I::Item: IntoIterator,
+    ItemOf<I>: Ord,
+{
+    kmerge_stable_by(iters, Ord::cmp)
+}
+
+/// Merges iterators sorted by `cmp` into one iterator sorted by `cmp`, smallest
+/// first. Items for which `cmp` returns `Ordering::Equal` are returned in the
+/// order of the iterators they come from
+pub fn kmerge_stable_by<I, F>(iters: I, cmp: F) -> KMergeStable<IterOf<I>, F>
+where
+    I: IntoIterator,
+    I::Item: IntoIterator,
+    F: Fn(&ItemOf<I>, &ItemOf<I>) -> Ordering,
+{
+    let mut iters: Vec<_> = iters.into_iter().map(|i| i.into_iter()).collect();
+
+    let mut heap = StableBinaryHeap::with_capacity_and_comparator(iters.len(), HeadComparator(cmp));
+    for (pos, iter) in iters.iter_mut().enumerate() {
+        if let Some(item) = iter.next() {
+            heap.push((item, pos));
+        }
+    }
+
+    KMergeStable { iters, heap }
+}
+
+type IterOf<I> = <<I as IntoIterator>::Item as IntoIterator>::IntoIter;
+type ItemOf<I> = <<I as IntoIterator>::Item as IntoIterator>::Item;
+type OrdFn<T> = fn(&T, &T) -> Ordering;
+
+/// Iterator returned by [`kmerge_stable`] and [`kmerge_stable_by`]
+pub struct KMergeStable<I: Iterator, F> {
+    iters: Vec<I>,
+    /// The next item of every iterator that isn't exhausted, together with the
+    /// iterator's position
+    heap: StableBinaryHeap<(I::Item, usize), HeadComparator<F>>,
+}
+
+/// Orders the heads of the merged iterators smallest first and equal heads by
+/// the position of their iterator
+struct HeadComparator<F>(F);
+
+impl<T, F: Fn(&T, &T) -> Ordering> Compare<(T, usize)> for HeadComparator<F> {
+    #[inline]
+    fn compare(&self, a: &(T, usize), b: &(T, usize)) -> Ordering {
+        (self.0)(&b.0, &a.0).then(b.1.cmp(&a.1))
+    }
+}
+
+impl<I, F> Iterator for KMergeStable<I, F>
+where
+    I: Iterator,
+    F: Fn(&I::Item, &I::Item) -> Ordering,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let (item, pos) = self.heap.pop()?;
+        if let Some(next) = self.iters[pos].next() {
+            self.heap.push((next, pos));
+        }
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iters
+            .iter()
+            .fold((self.heap.len(), Some(self.heap.len())), |(lo, hi), i| {
+                let (i_lo, i_hi) = i.size_hint();
+                let hi = hi.zip(i_hi).and_then(|(a, b)| a.checked_add(b));
+                (lo.saturating_add(i_lo), hi)
+            })
+    }
+}
+
+impl<I, F> FusedIterator for KMergeStable<I, F>
+where
+    I: FusedIterator,
+    F: Fn(&I::Item, &I::Item) -> Ordering,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kmerge_stable, kmerge_stable_by};
+    use alloc::{vec, vec::Vec};
+
+    #[test]
+    fn test_kmerge_stable() {
+        let iters = vec![
+            vec![(1, 0), (4, 0), (4, 1), (9, 0)],
+            vec![],
+            vec![(0, 2), (4, 2), (9, 2)],
+            vec![(4, 3)],
+        ];
+
+        let mut expected: Vec<_> = iters.iter().flatten().copied().collect();
+        expected.sort_by_key(|i| i.0);
+
+        let merged = kmerge_stable_by(iters.clone(), |a: &(u32, u32), b| a.0.cmp(&b.0));
+        assert_eq!(merged.size_hint(), (8, Some(8)));
+        assert_eq!(merged.collect::<Vec<_>>(), expected);
+
+        let merged: Vec<_> = kmerge_stable(iters).collect();
+        assert_eq!(merged.len(), 8);
+        assert!(merged.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    counter::Counter,
+    handle::Handle,
+    StableBinaryHeap,
+};
+use alloc::collections::BTreeSet;
+use core::fmt::{self, Debug};
+
+/// Stable binary heap with cheap cancellation of pushed items. Cancelling an
+/// item only marks it as dead, dead items are skipped when popping and removed
+/// from the heap once they outnumber the live ones.
+///
+/// Handles become invalid when the counter overflows or the heap gets
+/// cleared. Invalid handles are detected, cancelling with them returns `false`
+#[derive(Clone)]
+pub struct StableLazyHeap<T, C = MaxComparator, N = usize> {
+    /// Live and dead items. The top item is always live
+    heap: StableBinaryHeap<T, C, N>,
+    /// Counters of the items that haven't been popped or cancelled
+    live: BTreeSet<N>,
+}
+
+impl<T: Ord> StableLazyHeap<T> {
+    /// Creates a new empty heap
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_comparator(MaxComparator)
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> StableLazyHeap<T, C, N> {
+    /// Creates a new empty heap ordered by `cmp`
+    #[inline]
+    pub fn from_comparator(cmp: C) -> Self {
+        Self {
+            heap: StableBinaryHeap::from_comparator(cmp),
+            live: BTreeSet::new(),
+        }
+    }
+
+    /// Pushes a new item and returns a handle that can be used to cancel it
+    pub fn push(&mut self, item: T) -> Handle<N> {
+        if self.heap.counter() == N::MAX {
+            // Drop the dead items first so that the renumbered counters can
+            // all be marked live again
+            self.compact();
+            self.heap.renormalize();
+            self.live = self.heap.counters.iter().copied().collect();
+        }
+
+        let handle = self.heap.push_with_handle(item);
+        self.live.insert(handle.seq);
+        handle
+    }
+
+    /// Cancels the item referred to by `handle` in O(log n). Returns `false` if
+    /// the item has already been popped or cancelled
+    pub fn cancel(&mut self, handle: Handle<N>) -> bool {
+        if handle.epoch != self.heap.epoch || !self.live.remove(&handle.seq) {
+            return false;
+        }
+
+        self.skip_dead();
+        if self.dead_len() > self.live.len() {
+            self.compact();
+        }
+        true
+    }
+
+    /// Returns `true` if the item referred to by `handle` hasn't been popped or
+    /// cancelled yet
+    #[inline]
+    pub fn contains_handle(&self, handle: Handle<N>) -> bool {
+        handle.epoch == self.heap.epoch && self.live.contains(&handle.seq)
+    }
+
+    /// Removes the greatest live item and returns it
+    pub fn pop(&mut self) -> Option<T> {
+        let top = self.heap.pop_raw()?;
+        self.live.remove(&top.counter);
+        self.skip_dead();
+        Some(top.into_inner())
+    }
+
+    /// Returns the greatest live item
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.peek()
+    }
+
+    /// Returns the amount of live items
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.live.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.live.is_empty()
+    }
+
+    /// Returns the amount of cancelled items that are still stored in the heap
+    #[inline]
+    pub fn dead_len(&self) -> usize {
+        self.heap.len() - self.live.len()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.live.clear();
+    }
+
+    /// Returns an iterator over all live items in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.heap
+            .items
+            .iter()
+            .zip(self.heap.counters.iter())
+            .filter(|(_, counter)| self.live.contains(counter))
+            .map(|(item, _)| item)
+    }
+
+    /// Removes all cancelled items from the heap in O(n)
+    pub fn compact(&mut self) {
+        if self.dead_len() == 0 {
+            return;
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(dead = self.dead_len(), live = self.live.len(), "compact");
+
+        let live = &self.live;
+        self.heap.retain_raw(|_, counter| live.contains(&counter));
+        self.heap.rebuild();
+    }
+
+    /// Pops dead items until the top item is live
+    fn skip_dead(&mut self) {
+        while let Some((_, seq)) = self.heap.peek_with_seq() {
+            if self.live.contains(&seq) {
+                break;
+            }
+            self.heap.pop_raw();
+        }
+    }
+}
+
+impl<T: Debug, C: Compare<T>, N: Counter> Debug for StableLazyHeap<T, C, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord> Default for StableLazyHeap<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> Extend<T> for StableLazyHeap<T, C, N> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for i in iter {
+            self.push(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableLazyHeap;
+    use crate::{
+        compare::KeyComparator,
+        test_util::{keyed_items, sorted_desc},
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_lazy_heap() {
+        let items = keyed_items(200, 11);
+        let mut heap: StableLazyHeap<_, _> =
+            StableLazyHeap::from_comparator(KeyComparator(|i: &(u32, u32)| i.0));
+        let handles: Vec<_> = items.iter().map(|i| heap.push(*i)).collect();
+
+        for i in (0..200).filter(|i| i % 4 != 0) {
+            assert!(heap.cancel(handles[i]));
+            assert!(!heap.cancel(handles[i]));
+            assert!(heap.dead_len() <= heap.len() + 1);
+        }
+        assert_eq!(heap.len(), 50);
+
+        let expected = sorted_desc(items.into_iter().filter(|i| i.1 % 4 == 0));
+
+        let top = expected[0];
+        assert_eq!(heap.peek(), Some(&top));
+        assert_eq!(heap.pop(), Some(top));
+        assert!(!heap.cancel(handles[top.1 as usize]));
+
+        let mut popped = Vec::new();
+        while let Some(i) = heap.pop() {
+            popped.push(i);
+        }
+        assert_eq!(popped, expected[1..]);
+        assert_eq!(heap.dead_len(), 0);
+    }
+
+    #[test]
+    fn test_lazy_heap_overflow() {
+        let mut heap: StableLazyHeap<u32, _, u8> =
+            StableLazyHeap::from_comparator(KeyComparator(|i: &u32| i / 10));
+        let mut handles = Vec::new();
+        for i in 0..1000 {
+            handles.push(heap.push(i));
+            if i % 2 == 1 {
+                heap.cancel(handles[i as usize - 1]);
+            }
+            if i > 100 {
+                heap.pop();
+            }
+        }
+        assert_eq!(heap.len(), heap.iter().count());
+        assert!(heap.len() <= 100);
+    }
+
+    #[test]
+    fn test_lazy_heap_stale_handle() {
+        let mut heap: StableLazyHeap<u32, _, u8> =
+            StableLazyHeap::from_comparator(crate::compare::MaxComparator);
+        heap.push(1000);
+        heap.pop();
+        let stale = heap.push(0);
+        for i in 1..254 {
+            heap.push(i);
+            assert_eq!(heap.pop(), Some(i));
+        }
+
+        // Renumbers the counters, so the new item gets the old item's counter
+        let fresh = heap.push(7);
+        assert_eq!(fresh.seq(), stale.seq());
+        assert!(!heap.contains_handle(stale));
+        assert!(!heap.cancel(stale));
+        assert!(heap.cancel(fresh));
+        assert_eq!(heap.pop(), Some(0));
+
+        let handle = heap.push(5);
+        heap.clear();
+        heap.push(6);
+        assert!(!heap.cancel(handle));
+        assert_eq!(heap.len(), 1);
+    }
+}
@@ -1,286 +1,3159 @@
+#![no_std]
+
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impl;
+pub mod array;
+#[cfg(all(feature = "async", feature = "std"))]
+pub mod async_delay;
+pub mod bounded;
+mod buffer;
+pub mod calendar;
+#[cfg(feature = "std")]
+pub mod channel;
+pub mod compare;
+#[cfg(feature = "std")]
+pub mod concurrent;
+pub mod counted;
+pub mod counter;
+pub mod cow;
+pub mod dary;
+#[cfg(feature = "std")]
+pub mod dedup;
+pub mod delay;
+pub mod expiring;
+pub mod float;
+pub mod handle;
+pub mod indexed;
+pub mod integrity;
 pub mod item;
-
+mod kmerge;
+pub mod lazy;
+pub mod median;
+pub mod min_max;
+pub mod persistent;
+pub mod priority_queue;
+pub mod radix;
+#[cfg(feature = "rayon")]
+mod rayon_impl;
+#[cfg(feature = "rkyv")]
+mod rkyv_impl;
+#[cfg(feature = "serde")]
+mod serde_impl;
+pub mod size;
+mod sort;
+#[cfg(feature = "spill")]
+pub mod spill;
+mod stats;
+#[cfg(feature = "async")]
+pub mod stream;
+#[cfg(test)]
+mod test_util;
+pub mod top_k;
+#[cfg(feature = "std")]
+mod wait;
+pub mod wheel;
+pub mod window;
+
+pub use array::{CapacityError, StableArrayHeap};
+#[cfg(all(feature = "async", feature = "std"))]
+pub use async_delay::StableDelayQueue;
+pub use bounded::StableBoundedHeap;
+pub use buffer::{Allocator, Global, TryReserveError};
+pub use calendar::CalendarQueue;
+#[cfg(feature = "std")]
+pub use channel::{bounded_stable_priority_channel, stable_priority_channel};
+#[cfg(feature = "std")]
+pub use concurrent::{StableConcurrentHeap, SyncStableHeap};
+pub use counted::StableCountedHeap;
+pub use cow::StableCowHeap;
+pub use dary::StableDaryHeap;
+#[cfg(feature = "std")]
+pub use dedup::StableDedupHeap;
+pub use delay::DelayQueue;
+pub use expiring::StableExpiringHeap;
+pub use float::{TotalF32, TotalF64};
+pub use indexed::StableIndexedHeap;
+pub use integrity::IntegrityError;
+pub use kmerge::{kmerge_stable, kmerge_stable_by, KMergeStable};
+pub use lazy::StableLazyHeap;
+pub use median::StableMedianHeap;
+pub use min_max::StableMinMaxHeap;
+pub use priority_queue::StablePriorityQueue;
+pub use radix::StableRadixHeap;
+pub use size::HeapSize;
+pub use sort::{
+    partial_sort, partial_sort_by, select_nth_stable, select_nth_stable_by, stable_heapsort,
+    stable_heapsort_by, stable_heapsort_by_key,
+};
+#[cfg(feature = "spill")]
+pub use spill::SpillableStableHeap;
+#[cfg(feature = "stats")]
+pub use stats::Stats;
+#[cfg(feature = "async")]
+pub use stream::IntoStreamSorted;
+pub use top_k::TopK;
+pub use wheel::TimingWheel;
+pub use window::StableWindowTopK;
+
+use alloc::{
+    collections::BinaryHeap,
+    vec,
+    vec::{IntoIter, Vec},
+};
+use buffer::Buffer;
+use compare::{Ascending, Compare, FnComparator, KeyComparator, MaxComparator, MinComparator};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    slice,
+};
+use counter::{counter_add, counter_from_usize, Counter};
+use handle::Handle;
 use item::HeapItem;
-use std::{collections::BinaryHeap, vec::IntoIter};
-
-/// Normal Binary (Max) heap from std::collections::BinaryHeap but returns
-/// equal items in inserted order
-pub struct StableBinaryHeap<T> {
-    heap: BinaryHeap<HeapItem<T>>,
-    counter: usize,
+#[cfg(not(feature = "stats"))]
+use stats::Stats;
+
+/// Binary (Max) heap like std::collections::BinaryHeap but returns
+/// equal items in inserted order.
+///
+/// `N` is the integer type used to number the items in insertion order, see
+/// [`Counter`] for choosing a width. `A` is the allocator of the backing storage,
+/// which can only be changed with the `allocator-api2` feature
+#[derive(Clone)]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize))]
+pub struct StableBinaryHeap<T, C = MaxComparator, N = usize, A: Allocator = Global> {
+    /// Items in heap order
+    items: Buffer<T, A>,
+    /// Counters of the items at the same positions. Stored apart from the
+    /// items so that sifting small items touches fewer cache lines
+    counters: Buffer<N, A>,
+    counter: N,
+    /// Increased whenever the counters get renumbered or reset, so that
+    /// handles to the old counters can be told apart
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    epoch: u32,
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    cmp: C,
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    stats: Stats,
 }
 
+/// Binary (Min) heap returning the smallest item first. Equal items are still
+/// returned in inserted order
+pub type StableBinaryMinHeap<T> = StableBinaryHeap<T, MinComparator>;
+
+/// Binary heap ordered by the comparison function `F`. Items for which `F`
+/// returns `Ordering::Equal` are returned in inserted order
+pub type StableBinaryHeapBy<T, F> = StableBinaryHeap<T, FnComparator<F>>;
+
 impl<T: Ord> StableBinaryHeap<T> {
-    /// Creates a new stable binary heap
+    /// Creates a new stable binary heap. Doesn't allocate, so it can be used
+    /// to initialize a `static`
     #[inline]
-    pub fn new() -> Self {
-        let heap = BinaryHeap::new();
-        Self { heap, counter: 0 }
+    pub const fn new() -> Self {
+        Self {
+            items: Buffer::new(),
+            counters: Buffer::new(),
+            counter: 0,
+            epoch: 0,
+            cmp: MaxComparator,
+            stats: Stats::new(),
+        }
     }
 
     /// Creates a new stable binary heap with a given capacity
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self {
-        let heap = BinaryHeap::with_capacity(capacity);
-        Self { heap, counter: 0 }
+        Self::with_capacity_and_comparator(capacity, MaxComparator)
     }
 
-    /// Pushes a new element on the heap
+    /// Creates a new stable binary heap out of `vec` in O(n). Equal items are
+    /// returned in the order they appear in `vec`
     #[inline]
-    pub fn push(&mut self, item: T) {
-        let heap_item = self.new_item(item);
-        self.counter += 1;
-        self.heap.push(heap_item);
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        Self::from_vec_and_comparator(vec, MaxComparator)
     }
+}
 
+impl<T: Ord> StableBinaryHeap<T, MinComparator> {
+    /// Creates a new stable binary min heap. Doesn't allocate, so it can be
+    /// used to initialize a `static`
     #[inline]
-    fn push_raw(&mut self, item: HeapItem<T>) {
-        self.counter = self.counter.max(item.counter);
-        self.heap.push(item);
+    pub const fn new_min() -> Self {
+        Self {
+            items: Buffer::new(),
+            counters: Buffer::new(),
+            counter: 0,
+            epoch: 0,
+            cmp: MinComparator,
+            stats: Stats::new(),
+        }
     }
 
-    /// Returns a new HeapItem based wrapping around `inner`.
-    /// The StableBinaryHeap's `counter` has to be manually increased after each call
+    /// Creates a new stable binary min heap with a given capacity
     #[inline]
-    fn new_item(&self, inner: T) -> HeapItem<T> {
-        let id = self.counter;
-        HeapItem::new(inner, id)
+    pub fn with_capacity_min(capacity: usize) -> Self {
+        Self::with_capacity_and_comparator(capacity, MinComparator)
     }
+}
 
+impl<T: Ord, N: Counter, A: Allocator> StableBinaryHeap<T, MaxComparator, N, A> {
+    /// Converts the heap into a `std::collections::BinaryHeap`, dropping the
+    /// insertion order of equal items
     #[inline]
-    pub fn capacity(&self) -> usize {
-        self.heap.capacity()
+    pub fn into_binary_heap(self) -> BinaryHeap<T> {
+        BinaryHeap::from(self.into_vec())
     }
+}
 
+impl<T: PartialEq, C: Compare<T>, N: Counter, A: Allocator> StableBinaryHeap<T, C, N, A> {
+    /// Returns `true` if the heap contains an item equal to `item`
     #[inline]
-    pub fn len(&self) -> usize {
-        self.heap.len()
+    pub fn contains(&self, item: &T) -> bool {
+        self.items.iter().any(|i| i == item)
     }
+}
 
+impl<T, F> StableBinaryHeap<T, FnComparator<F>>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    /// Creates a new stable binary heap which orders its items using `cmp`
     #[inline]
-    pub fn clear(&mut self) {
-        self.heap.clear();
-        self.counter = 0;
+    pub fn new_by(cmp: F) -> Self {
+        Self::from_comparator(FnComparator(cmp))
     }
 
+    /// Creates a new stable binary heap with a given capacity which orders its
+    /// items using `cmp`
     #[inline]
-    pub fn is_empty(&self) -> bool {
-        self.heap.is_empty()
+    pub fn with_capacity_by(capacity: usize, cmp: F) -> Self {
+        Self::with_capacity_and_comparator(capacity, FnComparator(cmp))
     }
+}
 
+impl<T, K, F> StableBinaryHeap<T, KeyComparator<F>>
+where
+    F: Fn(&T) -> K,
+    K: Ord,
+{
+    /// Creates a new stable binary heap which orders its items by the key
+    /// returned from `f`. Items with equal keys are returned in inserted order
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item = &T> {
-        self.heap.iter().map(|i| i.inner())
+    pub fn new_by_key(f: F) -> Self {
+        Self::from_comparator(KeyComparator(f))
     }
 
+    /// Creates a new stable binary heap with a given capacity which orders its
+    /// items by the key returned from `f`
     #[inline]
-    pub fn peek_mut(&mut self) -> Option<std::collections::binary_heap::PeekMut<'_, HeapItem<T>>> {
-        self.heap.peek_mut()
+    pub fn with_capacity_by_key(capacity: usize, f: F) -> Self {
+        Self::with_capacity_and_comparator(capacity, KeyComparator(f))
     }
+}
 
+impl<T, C: Compare<T>, N: Counter> StableBinaryHeap<T, C, N> {
+    /// Creates a new stable binary heap ordered by `cmp`. Can be used to create
+    /// heaps with a counter type other than `usize`
     #[inline]
-    pub fn reserve(&mut self, additional: usize) {
-        self.heap.reserve(additional)
+    pub fn from_comparator(cmp: C) -> Self {
+        Self::from_comparator_in(cmp, Global)
     }
 
+    /// Creates a new stable binary heap with a given capacity ordered by `cmp`
     #[inline]
-    pub fn shrink_to(&mut self, min_capacity: usize) {
-        self.heap.shrink_to(min_capacity)
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> Self {
+        Self::with_capacity_and_comparator_in(capacity, cmp, Global)
     }
 
     #[inline]
-    pub fn shrink_to_fit(&mut self) {
-        self.heap.shrink_to_fit()
+    fn from_vec_and_comparator(vec: Vec<T>, cmp: C) -> Self {
+        Self::from_vec_and_comparator_in(vec, cmp, Global)
+    }
+
+    /// Rebuilds a heap from the parts returned by `into_raw_parts`, without
+    /// assigning new counters. Fails if the counters aren't unique or not below
+    /// `counter`
+    pub fn from_raw_parts(
+        items: Vec<(T, N)>,
+        counter: N,
+        cmp: C,
+    ) -> Result<Self, IntegrityError<N>> {
+        let (items, counters) = items.into_iter().unzip();
+        let mut heap = Self {
+            items: Buffer::from_vec_in(items, Global),
+            counters: Buffer::from_vec_in(counters, Global),
+            counter,
+            epoch: 0,
+            cmp,
+            stats: Stats::new(),
+        };
+        heap.check_counters()?;
+        heap.rebuild();
+        Ok(heap)
     }
+}
 
+impl<T: Ord, A: Allocator + Clone> StableBinaryHeap<T, MaxComparator, usize, A> {
+    /// Creates a new stable binary heap storing its items in `alloc`
     #[inline]
-    pub fn into_vec(self) -> Vec<T> {
-        self.heap
-            .into_vec()
-            .into_iter()
-            .map(|i| i.into_inner())
-            .collect()
+    pub fn new_in(alloc: A) -> Self {
+        Self::from_comparator_in(MaxComparator, alloc)
     }
 
+    /// Creates a new stable binary heap with a given capacity storing its items
+    /// in `alloc`
     #[inline]
-    pub fn into_sorted_vec(self) -> Vec<T> {
-        self.into_iter_sorted().collect()
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Self::with_capacity_and_comparator_in(capacity, MaxComparator, alloc)
     }
+}
 
+impl<T, C: Compare<T>, N: Counter, A: Allocator + Clone> StableBinaryHeap<T, C, N, A> {
+    /// Creates a new stable binary heap ordered by `cmp` storing its items in
+    /// `alloc`
     #[inline]
-    pub fn into_iter_sorted(self) -> IntoIterSorted<T> {
-        IntoIterSorted { inner: self.heap }
+    pub fn from_comparator_in(cmp: C, alloc: A) -> Self {
+        Self::with_capacity_and_comparator_in(0, cmp, alloc)
     }
 
+    /// Creates a new stable binary heap with a given capacity ordered by `cmp`
+    /// storing its items in `alloc`
     #[inline]
-    pub fn pop(&mut self) -> Option<T> {
-        self.heap.pop().map(|i| i.into_inner())
+    pub fn with_capacity_and_comparator_in(capacity: usize, cmp: C, alloc: A) -> Self {
+        Self {
+            items: Buffer::with_capacity_in(capacity, alloc.clone()),
+            counters: Buffer::with_capacity_in(capacity, alloc),
+            counter: N::ZERO,
+            epoch: 0,
+            cmp,
+            stats: Stats::new(),
+        }
+    }
+
+    /// Builds a heap out of `vec` assigning the counters by index
+    fn from_vec_and_comparator_in(vec: Vec<T>, cmp: C, alloc: A) -> Self {
+        let counters: Vec<N> = (0..vec.len()).map(counter_from_usize).collect();
+        let counter = counter_from_usize(vec.len());
+
+        let mut heap = Self {
+            items: Buffer::from_vec_in(vec, alloc.clone()),
+            counters: Buffer::from_vec_in(counters, alloc),
+            counter,
+            epoch: 0,
+            cmp,
+            stats: Stats::new(),
+        };
+        heap.rebuild();
+        heap
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> StableBinaryHeap<T, C, N, A> {
+    /// Returns a reference to the allocator of the heap's storage
+    #[inline]
+    pub fn allocator(&self) -> &A {
+        self.items.allocator()
     }
 
+    /// Pushes a new element on the heap
     #[inline]
-    pub fn peek(&self) -> Option<&T> {
-        self.heap.peek().map(|i| i.inner())
+    pub fn push(&mut self, item: T) {
+        let heap_item = self.new_item(item);
+        self.push_raw(heap_item);
+    }
+
+    /// Pushes `item` on the heap and pops the greatest item afterwards, using
+    /// at most one sift. If `item` is equal to the current greatest item, the
+    /// older one gets returned
+    pub fn push_pop(&mut self, item: T) -> T {
+        let mut item = self.new_item(item);
+
+        if !self.is_empty()
+            && self.cmp.compare_parts(
+                &item.inner,
+                &item.counter,
+                &self.items[0],
+                &self.counters[0],
+            ) == Ordering::Less
+        {
+            item = self.replace_at(0, item);
+            self.sift_down(0);
+        }
+
+        item.into_inner()
+    }
+
+    /// Replaces the greatest item with `item` and returns the replaced one,
+    /// using a single sift. `item` is treated as newly pushed. Pushes `item`
+    /// and returns `None` if the heap is empty
+    pub fn replace(&mut self, item: T) -> Option<T> {
+        let item = self.new_item(item);
+
+        if self.is_empty() {
+            self.push_unsifted(item);
+            return None;
+        }
+
+        let top = self.replace_at(0, item);
+        self.sift_down(0);
+        Some(top.into_inner())
+    }
+
+    /// Pushes a new element on the heap and returns a `Handle` referring to it.
+    /// Looking up a handle scans all items in O(n), so for decrease-key heavy
+    /// workloads like Dijkstra's algorithm use `StablePriorityQueue` instead
+    #[inline]
+    pub fn push_with_handle(&mut self, item: T) -> Handle<N> {
+        let item = self.new_item(item);
+        let handle = Handle::new(item.counter, self.epoch);
+        self.push_raw(item);
+        handle
+    }
+
+    /// Returns `true` if the item referred to by `handle` is still in the heap.
+    /// Takes O(n) to find the item
+    #[inline]
+    pub fn contains_handle(&self, handle: Handle<N>) -> bool {
+        self.position_of(handle).is_some()
+    }
+
+    /// Returns a reference to the item referred to by `handle` if it is still
+    /// in the heap. Takes O(n) to find the item
+    #[inline]
+    pub fn get(&self, handle: Handle<N>) -> Option<&T> {
+        self.position_of(handle).map(|pos| &self.items[pos])
+    }
+
+    /// Changes the item referred to by `handle` with `f` and moves it to its new
+    /// position. The item keeps its insertion counter, so it stays ordered before
+    /// all equal items pushed after it. Returns `false` without calling `f` if
+    /// the item is no longer in the heap or the handle was issued before the
+    /// counters got reset or renumbered. Takes O(n) to find the item plus
+    /// O(log n) to move it
+    pub fn update<F>(&mut self, handle: Handle<N>, f: F) -> bool
+    where
+        F: FnOnce(&mut T),
+    {
+        let Some(pos) = self.position_of(handle) else {
+            return false;
+        };
+
+        f(&mut self.items[pos]);
+        if self.sift_up(pos) == pos {
+            self.sift_down(pos);
+        }
+        true
+    }
+
+    /// Removes the item referred to by `handle` and returns it if it is still
+    /// in the heap. Takes O(n) to find the item plus O(log n) to remove it
+    pub fn remove(&mut self, handle: Handle<N>) -> Option<T> {
+        let pos = self.position_of(handle)?;
+        Some(self.remove_at(pos).into_inner())
     }
 
-    pub fn retain<F>(&mut self, f: F)
+    /// Replaces the first item found for which `is_same` returns `true` with
+    /// `item` and moves it to its new position, returning the replaced item.
+    /// Like with `update`, the new item keeps the counter of the replaced one.
+    /// Pushes `item` if no item matches
+    pub fn push_or_update<F>(&mut self, item: T, is_same: F) -> Option<T>
     where
         F: Fn(&T) -> bool,
     {
-        let tmp: Vec<_> = self.heap.drain().filter(|i| f(&i)).collect();
+        let Some(pos) = self.items.iter().position(is_same) else {
+            self.push(item);
+            return None;
+        };
+
+        let old = mem::replace(&mut self.items[pos], item);
+        if self.sift_up(pos) == pos {
+            self.sift_down(pos);
+        }
+        Some(old)
+    }
+
+    /// Returns the position of the item referred to by `handle`, `None` if the
+    /// handle was issued before the counters got renumbered. Scans the
+    /// counters, which are densely packed, instead of keeping an index that
+    /// every sift would have to update
+    #[inline]
+    fn position_of(&self, handle: Handle<N>) -> Option<usize> {
+        if handle.epoch != self.epoch {
+            return None;
+        }
+        self.counters.iter().position(|c| *c == handle.seq)
+    }
+
+    #[inline]
+    fn push_raw(&mut self, item: HeapItem<T, N>) {
+        self.push_unsifted(item);
+        self.sift_up(self.len() - 1);
+    }
+
+    /// Appends `item` without restoring the heap property
+    #[inline]
+    fn push_unsifted(&mut self, item: HeapItem<T, N>) {
+        self.items.push(item.inner);
+        self.counters.push(item.counter);
+    }
+
+    /// Replaces the item at `pos` together with its counter and returns the
+    /// replaced one without restoring the heap property
+    #[inline]
+    fn replace_at(&mut self, pos: usize, item: HeapItem<T, N>) -> HeapItem<T, N> {
+        let inner = mem::replace(&mut self.items[pos], item.inner);
+        let counter = mem::replace(&mut self.counters[pos], item.counter);
+        HeapItem::new(inner, counter)
+    }
+
+    /// Removes the item at `pos` by moving the last item into its place,
+    /// without restoring the heap property
+    #[inline]
+    fn swap_remove(&mut self, pos: usize) -> HeapItem<T, N> {
+        let inner = self.items.swap_remove(pos);
+        HeapItem::new(inner, self.counters.swap_remove(pos))
+    }
+
+    /// Swaps the items at the positions `a` and `b` together with their counters
+    #[inline]
+    fn swap(&mut self, a: usize, b: usize) {
+        self.items.swap(a, b);
+        self.counters.swap(a, b);
+    }
+
+    /// Moves all items out of the heap in their internal order, keeping the
+    /// capacity and the heap's counter
+    fn take_items(&mut self) -> Vec<HeapItem<T, N>> {
+        self.items
+            .drain_all()
+            .zip(self.counters.drain_all())
+            .map(|(inner, counter)| HeapItem::new(inner, counter))
+            .collect()
+    }
 
-        assert!(self.is_empty());
+    /// Retains only the items for which `f` returns `true`, keeping the
+    /// relative order of the remaining items. `f` gets passed the counter of
+    /// each item too. Doesn't restore the heap property
+    pub(crate) fn retain_raw<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T, N) -> bool,
+    {
+        let mut kept = 0;
+        for pos in 0..self.len() {
+            if f(&mut self.items[pos], self.counters[pos]) {
+                self.swap(kept, pos);
+                kept += 1;
+            }
+        }
+        self.items.truncate(kept);
+        self.counters.truncate(kept);
+    }
 
-        for i in tmp {
-            self.push_raw(i);
+    /// Returns a new HeapItem based wrapping around `inner` and increases the
+    /// StableBinaryHeap's `counter`. Renormalizes the counters if the counter
+    /// would overflow
+    #[inline]
+    fn new_item(&mut self, inner: T) -> HeapItem<T, N> {
+        if self.counter == N::MAX {
+            self.renormalize();
         }
+
+        let id = self.counter;
+        self.counter = counter_add(self.counter, N::ONE);
+        self.stats.record_push();
+        HeapItem::new(inner, id)
     }
 
-    /// Get the stable binary heap's counter.
-    pub fn counter(&self) -> usize {
-        self.counter
+    /// Renumbers the counters of all items to `0..len` keeping their relative
+    /// insertion order and resets the heap's counter to `len`. This happens
+    /// automatically once the counter would overflow.
+    ///
+    /// Handles returned before renormalizing no longer refer to any item
+    pub fn renormalize(&mut self) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(len = self.len(), "renormalize");
+        self.counter = counter::renormalize_counters(&mut self.counters);
+        self.epoch = self.epoch.wrapping_add(1);
     }
-}
 
-pub struct Drain<'a, T> {
-    iter: std::collections::binary_heap::Drain<'a, HeapItem<T>>,
-}
+    /// Moves all items of `other` into `self`, leaving `other` empty. The items
+    /// of `other` are treated as if they were pushed after all items of `self`,
+    /// keeping their relative insertion order
+    pub fn append(&mut self, other: &mut Self) {
+        if self.counter.checked_add(other.counter).is_none() {
+            self.renormalize();
+            other.renormalize();
+        }
 
-impl<'a, T: Ord> Iterator for Drain<'a, T> {
-    type Item = T;
+        let offset = self.counter;
+        self.counter = counter_add(self.counter, other.counter);
+        other.counter = N::ZERO;
+        other.epoch = other.epoch.wrapping_add(1);
+
+        let start = self.len();
+        self.items.extend(other.items.drain_all());
+        self.counters
+            .extend(other.counters.drain_all().map(|counter| counter + offset));
+        self.rebuild_tail(start);
+    }
+
+    /// Combines both heaps into one, reusing the larger buffer and rebuilding
+    /// the heap once in O(n). Like with `append`, the items of `other` are
+    /// treated as if they were pushed after all items of `self`. The
+    /// comparator of `self` is kept
+    pub fn meld(mut self, mut other: Self) -> Self {
+        if self.counter.checked_add(other.counter).is_none() {
+            self.renormalize();
+            other.renormalize();
+        }
+
+        let offset = self.counter;
+        for counter in other.counters.iter_mut() {
+            *counter = *counter + offset;
+        }
+
+        let (mut items, mut counters, mut rest) = if self.len() >= other.len() {
+            (self.items, self.counters, (other.items, other.counters))
+        } else {
+            (other.items, other.counters, (self.items, self.counters))
+        };
+        items.extend(rest.0.drain_all());
+        counters.extend(rest.1.drain_all());
+
+        let mut heap = Self {
+            items,
+            counters,
+            counter: counter_add(offset, other.counter),
+            epoch: self.epoch,
+            cmp: self.cmp,
+            stats: self.stats,
+        };
+        heap.rebuild();
+        heap
+    }
 
     #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(|i| i.into_inner())
+    pub fn capacity(&self) -> usize {
+        self.items.capacity().min(self.counters.capacity())
     }
-}
 
-impl<T: Ord> IntoIterator for StableBinaryHeap<T> {
-    type Item = T;
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
 
-    type IntoIter = IntoIter<T>;
+    /// Removes all items and resets the counter. Handles returned before no
+    /// longer refer to any item
+    #[inline]
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.counters.clear();
+        self.counter = N::ZERO;
+        self.epoch = self.epoch.wrapping_add(1);
+    }
 
     #[inline]
-    fn into_iter(self) -> Self::IntoIter {
-        self.into_vec().into_iter()
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
     }
-}
 
-impl<T: Ord> Extend<T> for StableBinaryHeap<T> {
+    /// Returns an iterator over all items in arbitrary order
     #[inline]
-    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-        for i in iter {
-            self.push(i);
+    pub fn iter(&self) -> Iter<'_, T, N> {
+        Iter {
+            iter: self.items.iter(),
+            counters: PhantomData,
         }
     }
-}
 
-impl<T: Ord> Default for StableBinaryHeap<T> {
+    /// Returns the underlying items and their counters in arbitrary order. The
+    /// counter of the item at position `i` is the one at position `i`
     #[inline]
-    fn default() -> Self {
-        StableBinaryHeap::new()
+    pub fn as_slices(&self) -> (&[T], &[N]) {
+        (&self.items, &self.counters)
     }
-}
 
-pub struct IntoIterSorted<T> {
-    inner: BinaryHeap<HeapItem<T>>,
-}
+    /// Returns an iterator over references to all items in the same order as
+    /// `into_iter_sorted` would, without modifying the heap. Yielding k items
+    /// takes O(k log k)
+    #[inline]
+    pub fn iter_sorted(&self) -> IterSorted<'_, T, C, N, A> {
+        let mut frontier = StableBinaryHeap::from_comparator(IndexComparator { heap: self });
+        if !self.is_empty() {
+            frontier.push(0);
+        }
 
-impl<T: Ord> Iterator for IntoIterSorted<T> {
-    type Item = T;
+        IterSorted {
+            frontier,
+            remaining: self.len(),
+        }
+    }
 
+    /// Returns a mutable reference to the greatest item. The item keeps its
+    /// position in the insertion order, the heap gets restored once the
+    /// returned `StablePeekMut` is dropped
     #[inline]
-    fn next(&mut self) -> Option<T> {
-        self.inner.pop().map(|i| i.into_inner())
+    pub fn peek_mut(&mut self) -> Option<StablePeekMut<'_, T, C, N, A>> {
+        if self.is_empty() {
+            return None;
+        }
+
+        Some(StablePeekMut { heap: self })
     }
 
+    /// Returns a guard for mutating all items in arbitrary order through
+    /// `StableIterMut::iter`. The counters are kept and the heap gets rebuilt in
+    /// O(n) once the guard is dropped
     #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let exact = self.inner.len();
-        (exact, Some(exact))
+    pub fn iter_mut(&mut self) -> StableIterMut<'_, T, C, N, A> {
+        StableIterMut { heap: self }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use rand::{thread_rng, Rng};
+    /// Removes all items from the heap and returns them in arbitrary order.
+    /// Resets the counter just like `clear`
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, T, N, A> {
+        self.counter = N::ZERO;
+        self.epoch = self.epoch.wrapping_add(1);
+        self.counters.clear();
+        Drain {
+            iter: self.items.drain_all(),
+            counters: PhantomData,
+        }
+    }
 
-    use crate::*;
+    /// Removes all items from the heap and returns them in heap order. The
+    /// heap is empty once the returned iterator gets dropped, even if it wasn't
+    /// fully consumed
+    #[inline]
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, T, C, N, A> {
+        DrainSorted { heap: self }
+    }
 
-    fn generate_data(inp_len: usize) -> Vec<usize> {
-        let mut input = vec![0usize; inp_len];
-        thread_rng().try_fill(&mut input[..]).unwrap();
-        input
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.items.reserve(additional);
+        self.counters.reserve(additional);
     }
 
-    fn make_test(inp_len: usize) {
-        let input = generate_data(inp_len);
+    /// Tries to reserve capacity for at least `additional` more items,
+    /// returning an error instead of aborting if the allocation fails
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.items.try_reserve(additional)?;
+        self.counters.try_reserve(additional)
+    }
 
-        let mut expected = input.clone();
-        expected.sort_by(|a, b| a.cmp(&b).reverse());
+    /// Tries to reserve capacity for exactly `additional` more items,
+    /// returning an error instead of aborting if the allocation fails
+    #[inline]
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.items.try_reserve_exact(additional)?;
+        self.counters.try_reserve_exact(additional)
+    }
 
-        let mut stable_heap = StableBinaryHeap::new();
-        stable_heap.extend(input);
+    #[inline]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.items.shrink_to(min_capacity);
+        self.counters.shrink_to(min_capacity);
+    }
 
-        let out = stable_heap.into_iter_sorted().collect::<Vec<_>>();
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.items.shrink_to_fit();
+        self.counters.shrink_to_fit();
+    }
 
-        assert_eq!(out, expected);
+    /// Returns all items together with their counters in arbitrary order, and
+    /// the counter the next pushed item would get
+    pub fn into_raw_parts(self) -> (Vec<(T, N)>, N) {
+        let items = self
+            .items
+            .into_vec()
+            .into_iter()
+            .zip(self.counters.into_vec())
+            .collect();
+        (items, self.counter)
     }
 
-    #[test]
-    fn test_heap_functionality() {
-        for inp_len in (1..9000).step_by(51) {
-            make_test(inp_len);
-        }
+    #[inline]
+    pub fn into_vec(self) -> Vec<T> {
+        self.items.into_vec()
     }
 
-    #[test]
-    fn test_stability_same() {
-        let mut heap = StableBinaryHeap::new();
+    /// Returns all items in the order they were inserted
+    pub fn into_vec_insertion_order(mut self) -> Vec<T> {
+        let mut items = self.take_items();
+        items.sort_unstable_by_key(|i| i.counter);
+        items.into_iter().map(|i| i.into_inner()).collect()
+    }
 
-        for i in 0..1000 {
-            heap.push(UniqueItem::new(i, 0));
-        }
+    /// Returns all items sorted in heap order. Sorts the items in place and
+    /// reuses the heap's buffer unless the `allocator-api2` feature is enabled
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        self.sort_ascending();
+        self.items.reverse();
+        self.into_vec()
+    }
 
-        let vec = heap.into_sorted_vec();
-        for i in 0..1000 {
-            assert_eq!(vec[i].item, i as usize);
+    #[inline]
+    pub fn into_iter_sorted(self) -> IntoIterSorted<T, C, N, A> {
+        IntoIterSorted {
+            state: IntoIterSortedState::Heap(self),
         }
     }
 
-    #[test]
-    fn test_stability_simple() {
-        let mut heap = StableBinaryHeap::new();
+    /// Returns all items sorted smallest first. Equal items are returned in
+    /// the same order as when popping
+    pub fn into_sorted_vec_asc(self) -> Vec<T> {
+        self.into_ascending().into_sorted_vec()
+    }
 
-        heap.push(UniqueItem::new("9", 3));
-        heap.push(UniqueItem::new("8", 2));
-        heap.push(UniqueItem::new("7", 2));
-        heap.push(UniqueItem::new("a", 1));
-        heap.push(UniqueItem::new("b", 1));
-        heap.push(UniqueItem::new("c", 1));
-        heap.push(UniqueItem::new("d", 1));
-        heap.push(UniqueItem::new("e", 0));
+    /// Returns an iterator yielding all items smallest first. Equal items are
+    /// returned in the same order as when popping
+    #[inline]
+    pub fn into_iter_sorted_asc(self) -> IntoIterSortedAsc<T, C, N, A> {
+        IntoIterSortedAsc {
+            inner: self.into_ascending().into_iter_sorted(),
+        }
+    }
 
-        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
-        assert_eq!(out, vec!["9", "8", "7", "a", "b", "c", "d", "e"]);
+    /// Returns an iterator yielding the groups of equal items in heap order.
+    /// Each group is returned as its oldest item together with the remaining
+    /// items of the group in insertion order
+    #[inline]
+    pub fn into_groups_sorted(self) -> IntoGroupsSorted<T, C, N, A> {
+        IntoGroupsSorted { heap: self }
     }
 
-    #[test]
-    fn test_stability_full() {
-        for inp_len in (1..10000).step_by(71) {
-            new_stability_test(inp_len);
+    /// Rebuilds the heap with the order of `C` reversed, keeping the counters
+    fn into_ascending(self) -> StableBinaryHeap<T, Ascending<C>, N, A> {
+        let mut heap = StableBinaryHeap {
+            items: self.items,
+            counters: self.counters,
+            counter: self.counter,
+            epoch: self.epoch,
+            cmp: Ascending(self.cmp),
+            stats: self.stats,
+        };
+        heap.rebuild();
+        heap
+    }
+
+    /// Heapsorts the items in place, leaving the smallest item first. The
+    /// buffer is no longer a valid heap afterwards
+    fn sort_ascending(&mut self) {
+        let mut end = self.len();
+        while end > 1 {
+            end -= 1;
+            self.swap(0, end);
+            self.sift_down_range(0, end);
         }
     }
 
-    #[test]
-    fn test_retain() {
-        let mut heap = StableBinaryHeap::new();
-        for i in 0..=5 {
-            heap.push(i);
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        self.pop_raw().map(|i| i.into_inner())
+    }
+
+    /// Returns an iterator popping the greatest item as long as `pred` returns
+    /// `true` for it. Items not consumed from the iterator stay in the heap
+    pub fn pop_while<'a, F>(&'a mut self, mut pred: F) -> impl Iterator<Item = T> + 'a
+    where
+        F: FnMut(&T) -> bool + 'a,
+    {
+        core::iter::from_fn(move || self.pop_if(&mut pred))
+    }
+
+    /// Removes the greatest item and returns it if `pred` returns `true` for it
+    pub fn pop_if<F>(&mut self, pred: F) -> Option<T>
+    where
+        F: FnOnce(&T) -> bool,
+    {
+        if pred(self.peek()?) {
+            self.pop()
+        } else {
+            None
         }
+    }
 
-        heap.retain(|i| *i != 2);
+    /// Removes the `k` greatest items and returns them in heap order. If `k` is
+    /// a large fraction of the heap, the items get selected in O(n) instead of
+    /// being popped one by one
+    pub fn pop_k(&mut self, k: usize) -> Vec<T> {
+        self.pop_k_raw(k)
+            .into_iter()
+            .map(|i| i.into_inner())
+            .collect()
+    }
 
-        assert_eq!(heap.into_sorted_vec(), vec![5, 4, 3, 1, 0]);
+    /// Returns the `k` greatest items in heap order without removing them.
+    /// Runs in O(n log k) using a bounded heap of references
+    pub fn peek_k(&self, k: usize) -> Vec<&T> {
+        let cmp = FnComparator(|a: &usize, b: &usize| self.cmp_at(*a, *b));
+        let mut top: StableBoundedHeap<_, _> = StableBoundedHeap::from_comparator(k, cmp);
+        top.extend(0..self.len());
+        top.into_sorted_vec()
+            .into_iter()
+            .map(|pos| &self.items[pos])
+            .collect()
+    }
+
+    /// Returns the item at index `k` of the heap order without removing any
+    /// items, so `kth_largest(0)` is the greatest item. Only the subtrees that
+    /// can contain the `k + 1` greatest items are visited, taking O(k log k)
+    pub fn kth_largest(&self, k: usize) -> Option<&T> {
+        if k >= self.len() {
+            return None;
+        }
+
+        let cmp = FnComparator(|a: &usize, b: &usize| self.cmp_at(*a, *b));
+        let mut frontier: StableBinaryHeap<_, _> = StableBinaryHeap::from_comparator(cmp);
+        frontier.push(0);
+        for _ in 0..k {
+            let pos = frontier.pop()?;
+            frontier.extend(
+                [2 * pos + 1, 2 * pos + 2]
+                    .into_iter()
+                    .filter(|c| *c < self.len()),
+            );
+        }
+        frontier.peek().map(|pos| &self.items[*pos])
+    }
+
+    /// Returns the amount of items greater than or equal to `x`. Subtrees whose
+    /// root is smaller than `x` are skipped, so only the counted items and
+    /// their direct children get compared
+    pub fn count_ge(&self, x: &T) -> usize {
+        let mut count = 0;
+        let mut stack = vec![0];
+        while let Some(pos) = stack.pop() {
+            if pos < self.len() && self.cmp.compare(&self.items[pos], x) != Ordering::Less {
+                count += 1;
+                stack.extend([2 * pos + 1, 2 * pos + 2]);
+            }
+        }
+        count
+    }
+
+    /// Removes all items comparing equal to the greatest one and returns them
+    /// in insertion order
+    pub fn pop_tied_max(&mut self) -> Vec<T> {
+        let Some(top) = self.items.first() else {
+            return Vec::new();
+        };
+
+        // The tied items form a subtree at the root since each of their parents
+        // is greater or equal
+        let mut tied = 0;
+        let mut stack = vec![0];
+        while let Some(pos) = stack.pop() {
+            if pos < self.len() && self.cmp.compare(top, &self.items[pos]) == Ordering::Equal {
+                tied += 1;
+                stack.extend([2 * pos + 1, 2 * pos + 2]);
+            }
+        }
+
+        let mut group = self.pop_k_raw(tied);
+        group.sort_unstable_by_key(|i| i.counter);
+        group.into_iter().map(|i| i.into_inner()).collect()
+    }
+
+    /// Removes the `k` greatest items into a new heap and leaves the rest in
+    /// `self`. The moved items keep their counters and both heaps continue
+    /// counting from the current counter, so handles stay valid in either heap
+    pub fn split_off_top(&mut self, k: usize) -> Self
+    where
+        C: Clone,
+        A: Clone,
+    {
+        // Sorted greatest first, which already is a valid heap
+        let (items, counters) = self
+            .pop_k_raw(k)
+            .into_iter()
+            .map(|i| (i.inner, i.counter))
+            .unzip();
+        Self {
+            items: Buffer::from_vec_in(items, self.allocator().clone()),
+            counters: Buffer::from_vec_in(counters, self.allocator().clone()),
+            counter: self.counter,
+            epoch: self.epoch,
+            cmp: self.cmp.clone(),
+            stats: Stats::new(),
+        }
+    }
+
+    /// Drops all items except the `k` greatest in O(n). Of equal items the older
+    /// ones are kept. The remaining items keep their counters
+    pub fn truncate(&mut self, k: usize) {
+        if k >= self.len() {
+            return;
+        }
+        if k == 0 {
+            self.items.clear();
+            self.counters.clear();
+            return;
+        }
+
+        let mut items = self.take_items();
+        items.select_nth_unstable_by(k - 1, |a, b| self.cmp.compare_items(b, a));
+        for item in items.into_iter().take(k) {
+            self.push_unsifted(item);
+        }
+        self.rebuild();
+    }
+
+    /// Removes the `k` greatest items and returns them greatest first
+    fn pop_k_raw(&mut self, k: usize) -> Vec<HeapItem<T, N>> {
+        let len = self.len();
+        let k = k.min(len);
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let log_len = (usize::BITS - len.leading_zeros()) as usize;
+        if k * log_len <= len {
+            let mut out = Vec::with_capacity(k);
+            out.extend((0..k).filter_map(|_| self.pop_raw()));
+            return out;
+        }
+
+        let mut top = self.take_items();
+        top.select_nth_unstable_by(k - 1, |a, b| self.cmp.compare_items(b, a));
+        for item in top.drain(k..) {
+            self.push_unsifted(item);
+        }
+        self.stats.record_pops(k);
+        top.sort_unstable_by(|a, b| self.cmp.compare_items(b, a));
+
+        self.rebuild();
+        top
+    }
+
+    #[inline]
+    fn pop_raw(&mut self) -> Option<HeapItem<T, N>> {
+        if self.is_empty() {
+            return None;
+        }
+
+        self.stats.record_pops(1);
+        let item = self.swap_remove(0);
+        if !self.is_empty() {
+            self.sift_down(0);
+        }
+        Some(item)
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.items.first()
+    }
+
+    /// Calls `f` on the greatest item and restores the heap order afterwards.
+    /// Returns `None` if the heap is empty
+    pub fn modify_top<F, R>(&mut self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let top = self.items.first_mut()?;
+        let res = f(top);
+        self.sift_down(0);
+        Some(res)
+    }
+
+    /// Returns the greatest item together with its insertion number. Lower
+    /// numbers were pushed earlier
+    #[inline]
+    pub fn peek_with_seq(&self) -> Option<(&T, N)> {
+        self.items.first().map(|i| (i, self.counters[0]))
+    }
+
+    /// Retains only the items for which `f` returns `true`. The remaining items
+    /// keep their counters and the heap gets rebuilt in place in O(n)
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let len = self.len();
+        self.retain_raw(|i, _| f(i));
+
+        if self.len() < len {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(removed = len - self.len(), len = self.len(), "retain");
+            self.rebuild();
+        }
+    }
+
+    /// Removes all items smaller than `bound` in a single pass and returns them
+    /// in arbitrary order. The heap gets rebuilt once in O(n) afterwards
+    pub fn drain_below(&mut self, bound: &T) -> Vec<T> {
+        let mut removed = Vec::new();
+        let mut pos = 0;
+        while pos < self.len() {
+            if self.cmp.compare(&self.items[pos], bound) == Ordering::Less {
+                removed.push(self.swap_remove(pos).into_inner());
+            } else {
+                pos += 1;
+            }
+        }
+
+        if !removed.is_empty() {
+            self.rebuild();
+        }
+        removed
+    }
+
+    /// Returns an iterator that lazily removes and yields all items for which
+    /// `pred` returns `true`, in arbitrary order. The remaining items keep their
+    /// counters and the heap gets rebuilt in O(n) once the iterator is dropped,
+    /// even if it wasn't fully consumed
+    #[inline]
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, T, C, N, A, F>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        ExtractIf {
+            heap: self,
+            pos: 0,
+            removed: false,
+            pred,
+        }
+    }
+
+    /// Retains only the items for which `f` returns `true`, passing a mutable
+    /// reference to every item. Since `f` may change the order of the items,
+    /// the heap always gets rebuilt in O(n) afterwards
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        self.retain_raw(|i, _| f(i));
+        self.rebuild();
+    }
+
+    /// Returns a reference to an item for which `f` returns `true`. Items are
+    /// searched in arbitrary order
+    #[inline]
+    pub fn find<F>(&self, f: F) -> Option<&T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.items.iter().find(|i| f(i))
+    }
+
+    /// Removes the first item found for which `f` returns `true` and returns it.
+    /// Only the path of the removed item gets sifted, so this runs in O(n) for
+    /// the search plus O(log n) for the removal
+    pub fn remove_first<F>(&mut self, f: F) -> Option<T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let pos = self.items.iter().position(f)?;
+        Some(self.remove_at(pos).into_inner())
+    }
+
+    /// Removes the item at `pos` and restores the heap property
+    fn remove_at(&mut self, pos: usize) -> HeapItem<T, N> {
+        let item = self.swap_remove(pos);
+        if pos < self.len() && self.sift_up(pos) == pos {
+            self.sift_down(pos);
+        }
+
+        item
+    }
+
+    /// Get the stable binary heap's counter.
+    pub fn counter(&self) -> N {
+        self.counter
+    }
+
+    /// Restores the heap property for all items in O(n). Needed after the
+    /// order of items changed without the heap knowing about it, e.g. when
+    /// they are ordered by data behind a `RefCell`. Equal items keep their
+    /// insertion order since the counters are left untouched. Until this gets
+    /// called, `check_integrity` reports the misplaced items and all other
+    /// methods may return items in an unspecified order
+    pub fn rebuild(&mut self) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("rebuild", len = self.len()).entered();
+
+        let mut n = self.len() / 2;
+        while n > 0 {
+            n -= 1;
+            self.sift_down(n);
+        }
+        debug_assert!(self.check_integrity().is_ok());
+    }
+
+    /// Restores the heap property after items got added to `start..`. Either
+    /// sifts up each new item or rebuilds the whole heap, whichever is cheaper
+    fn rebuild_tail(&mut self, start: usize) {
+        let len = self.len();
+        if start == len {
+            return;
+        }
+
+        if len - start > start {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(added = len - start, len, "extend");
+            self.rebuild();
+            return;
+        }
+
+        for pos in start..len {
+            self.sift_up(pos);
+        }
+    }
+
+    /// Compares the items at the positions `a` and `b`
+    #[inline]
+    fn cmp_at(&self, a: usize, b: usize) -> Ordering {
+        self.cmp.compare_parts(
+            &self.items[a],
+            &self.counters[a],
+            &self.items[b],
+            &self.counters[b],
+        )
+    }
+
+    /// Moves the item at `pos` up until its parent is greater. Returns the new position
+    fn sift_up(&mut self, mut pos: usize) -> usize {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            self.stats.record_comparison();
+            if self.cmp_at(pos, parent) != Ordering::Greater {
+                break;
+            }
+
+            self.stats.record_sift();
+            self.swap(pos, parent);
+            pos = parent;
+        }
+
+        pos
+    }
+
+    /// Moves the item at `pos` down until both of its children are smaller.
+    /// Returns the new position
+    #[inline]
+    fn sift_down(&mut self, pos: usize) -> usize {
+        self.sift_down_range(pos, self.len())
+    }
+
+    /// Same as `sift_down` but only considers the items in `..end`
+    fn sift_down_range(&mut self, mut pos: usize, end: usize) -> usize {
+        loop {
+            let mut child = 2 * pos + 1;
+            if child >= end {
+                break;
+            }
+
+            if child + 1 < end {
+                self.stats.record_comparison();
+                if self.cmp_at(child + 1, child) == Ordering::Greater {
+                    child += 1;
+                }
+            }
+
+            self.stats.record_comparison();
+            if self.cmp_at(child, pos) != Ordering::Greater {
+                break;
+            }
+
+            self.stats.record_sift();
+            self.swap(pos, child);
+            pos = child;
+        }
+
+        pos
+    }
+}
+
+/// Shows the items in their internal order together with their counters. The comparator
+/// is omitted
+impl<T: Debug, C, N: Debug, A: Allocator> Debug for StableBinaryHeap<T, C, N, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableBinaryHeap")
+            .field("items", &self.items)
+            .field("counters", &self.counters)
+            .field("counter", &self.counter)
+            .finish()
+    }
+}
+
+/// Two heaps are equal if they return equal items in the same order. Neither
+/// the internal layout nor the counters have to match
+impl<T: PartialEq, C: Compare<T>, N: Counter, A: Allocator> PartialEq
+    for StableBinaryHeap<T, C, N, A>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter_sorted().eq(other.iter_sorted())
+    }
+}
+
+impl<T: Eq, C: Compare<T>, N: Counter, A: Allocator> Eq for StableBinaryHeap<T, C, N, A> {}
+
+/// Mutable reference to the greatest item of a `StableBinaryHeap`. The heap gets
+/// restored as soon as the `StablePeekMut` gets dropped
+pub struct StablePeekMut<'a, T, C: Compare<T>, N: Counter = usize, A: Allocator = Global> {
+    heap: &'a mut StableBinaryHeap<T, C, N, A>,
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> StablePeekMut<'a, T, C, N, A> {
+    /// Removes the peeked item from the heap and returns it
+    #[inline]
+    pub fn pop(this: Self) -> T {
+        // The heap is never empty while a `StablePeekMut` exists
+        this.heap.pop().unwrap()
+    }
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> Deref for StablePeekMut<'a, T, C, N, A> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.heap.items[0]
+    }
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> DerefMut for StablePeekMut<'a, T, C, N, A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.heap.items[0]
+    }
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> Drop for StablePeekMut<'a, T, C, N, A> {
+    #[inline]
+    fn drop(&mut self) {
+        let pos = self.heap.sift_down(0);
+        self.heap.debug_check_at(pos);
+    }
+}
+
+pub struct Iter<'a, T, N = usize> {
+    iter: slice::Iter<'a, T>,
+    counters: PhantomData<&'a N>,
+}
+
+impl<'a, T, N> Iterator for Iter<'a, T, N> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T, N> ExactSizeIterator for Iter<'a, T, N> {}
+
+impl<'a, T, N> FusedIterator for Iter<'a, T, N> {}
+
+impl<'a, T, N> Clone for Iter<'a, T, N> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            counters: PhantomData,
+        }
+    }
+}
+
+/// Guard returned by `StableBinaryHeap::iter_mut`. Rebuilds the heap when
+/// dropped
+pub struct StableIterMut<'a, T, C: Compare<T>, N: Counter = usize, A: Allocator = Global> {
+    heap: &'a mut StableBinaryHeap<T, C, N, A>,
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> StableIterMut<'a, T, C, N, A> {
+    /// Returns an iterator over mutable references to all items in arbitrary
+    /// order
+    #[inline]
+    pub fn iter(&mut self) -> IterMut<'_, T, N> {
+        IterMut {
+            iter: self.heap.items.iter_mut(),
+            counters: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'b, T, C: Compare<T>, N: Counter, A: Allocator> IntoIterator
+    for &'b mut StableIterMut<'a, T, C, N, A>
+{
+    type Item = &'b mut T;
+    type IntoIter = IterMut<'b, T, N>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> Drop for StableIterMut<'a, T, C, N, A> {
+    #[inline]
+    fn drop(&mut self) {
+        self.heap.rebuild();
+    }
+}
+
+pub struct IterMut<'a, T, N = usize> {
+    iter: slice::IterMut<'a, T>,
+    counters: PhantomData<&'a N>,
+}
+
+impl<'a, T, N> Iterator for IterMut<'a, T, N> {
+    type Item = &'a mut T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T, N> ExactSizeIterator for IterMut<'a, T, N> {}
+
+impl<'a, T, N> FusedIterator for IterMut<'a, T, N> {}
+
+pub struct Drain<'a, T, N = usize, A: Allocator = Global> {
+    iter: buffer::Drain<'a, T, A>,
+    counters: PhantomData<N>,
+}
+
+impl<'a, T, N, A: Allocator> Iterator for Drain<'a, T, N, A> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T, N, A: Allocator> ExactSizeIterator for Drain<'a, T, N, A> {}
+
+impl<'a, T, N, A: Allocator> FusedIterator for Drain<'a, T, N, A> {}
+
+pub struct DrainSorted<'a, T, C: Compare<T>, N: Counter = usize, A: Allocator = Global> {
+    heap: &'a mut StableBinaryHeap<T, C, N, A>,
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> Iterator for DrainSorted<'a, T, C, N, A> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.heap.pop()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let exact = self.heap.len();
+        (exact, Some(exact))
+    }
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> ExactSizeIterator
+    for DrainSorted<'a, T, C, N, A>
+{
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> FusedIterator for DrainSorted<'a, T, C, N, A> {}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> Drop for DrainSorted<'a, T, C, N, A> {
+    #[inline]
+    fn drop(&mut self) {
+        self.heap.clear();
+    }
+}
+
+pub struct ExtractIf<'a, T, C, N, A, F>
+where
+    C: Compare<T>,
+    N: Counter,
+    A: Allocator,
+    F: FnMut(&T) -> bool,
+{
+    heap: &'a mut StableBinaryHeap<T, C, N, A>,
+    /// Items before `pos` have been checked and are kept
+    pos: usize,
+    removed: bool,
+    pred: F,
+}
+
+impl<'a, T, C, N, A, F> Iterator for ExtractIf<'a, T, C, N, A, F>
+where
+    C: Compare<T>,
+    N: Counter,
+    A: Allocator,
+    F: FnMut(&T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while self.pos < self.heap.len() {
+            if (self.pred)(&self.heap.items[self.pos]) {
+                self.removed = true;
+                return Some(self.heap.swap_remove(self.pos).into_inner());
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.heap.len() - self.pos))
+    }
+}
+
+impl<'a, T, C, N, A, F> FusedIterator for ExtractIf<'a, T, C, N, A, F>
+where
+    C: Compare<T>,
+    N: Counter,
+    A: Allocator,
+    F: FnMut(&T) -> bool,
+{
+}
+
+impl<'a, T, C, N, A, F> Drop for ExtractIf<'a, T, C, N, A, F>
+where
+    C: Compare<T>,
+    N: Counter,
+    A: Allocator,
+    F: FnMut(&T) -> bool,
+{
+    #[inline]
+    fn drop(&mut self) {
+        if self.removed {
+            self.heap.rebuild();
+        }
+    }
+}
+
+/// Orders positions of a heap by the items they point to
+struct IndexComparator<'a, T, C, N, A: Allocator> {
+    heap: &'a StableBinaryHeap<T, C, N, A>,
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> Compare<usize>
+    for IndexComparator<'a, T, C, N, A>
+{
+    #[inline]
+    fn compare(&self, a: &usize, b: &usize) -> Ordering {
+        self.heap.cmp_at(*a, *b)
+    }
+}
+
+pub struct IterSorted<'a, T, C: Compare<T>, N: Counter = usize, A: Allocator = Global> {
+    /// Positions of the items that can be yielded next. Always contains the
+    /// children of all positions yielded so far
+    frontier: StableBinaryHeap<usize, IndexComparator<'a, T, C, N, A>>,
+    remaining: usize,
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> Iterator for IterSorted<'a, T, C, N, A> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        let pos = self.frontier.pop()?;
+        let heap = self.frontier.cmp.heap;
+
+        for child in [2 * pos + 1, 2 * pos + 2] {
+            if child < heap.len() {
+                self.frontier.push(child);
+            }
+        }
+
+        self.remaining -= 1;
+        Some(&heap.items[pos])
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> ExactSizeIterator
+    for IterSorted<'a, T, C, N, A>
+{
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> FusedIterator for IterSorted<'a, T, C, N, A> {}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> IntoIterator for StableBinaryHeap<T, C, N, A> {
+    type Item = T;
+
+    type IntoIter = IntoIter<T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<'a, T, C: Compare<T>, N: Counter, A: Allocator> IntoIterator
+    for &'a StableBinaryHeap<T, C, N, A>
+{
+    type Item = &'a T;
+
+    type IntoIter = Iter<'a, T, N>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> Extend<T> for StableBinaryHeap<T, C, N, A> {
+    /// Appends the items and restores the heap order afterwards, rebuilding the
+    /// whole heap in O(n) if more items are added than it already holds.
+    /// Reserves space for the lower bound of the iterator's `size_hint` up front
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+
+        let start = self.len();
+        for i in iter {
+            let item = self.new_item(i);
+            self.push_unsifted(item);
+        }
+        self.rebuild_tail(start);
+    }
+}
+
+impl<'a, T: Copy + 'a, C: Compare<T>, N: Counter, A: Allocator> Extend<&'a T>
+    for StableBinaryHeap<T, C, N, A>
+{
+    #[inline]
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T, C: Compare<T> + Default, N: Counter> FromIterator<T> for StableBinaryHeap<T, C, N> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<T, C: Compare<T> + Default, N: Counter> From<Vec<T>> for StableBinaryHeap<T, C, N> {
+    #[inline]
+    fn from(vec: Vec<T>) -> Self {
+        Self::from_vec_and_comparator(vec, C::default())
+    }
+}
+
+/// Equal items are treated as inserted in the order `BinaryHeap::into_vec`
+/// returns them
+impl<T: Ord, N: Counter> From<BinaryHeap<T>> for StableBinaryHeap<T, MaxComparator, N> {
+    #[inline]
+    fn from(heap: BinaryHeap<T>) -> Self {
+        Self::from_vec_and_comparator(heap.into_vec(), MaxComparator)
+    }
+}
+
+impl<T, C, N, A> Default for StableBinaryHeap<T, C, N, A>
+where
+    C: Compare<T> + Default,
+    N: Counter,
+    A: Allocator + Default,
+{
+    #[inline]
+    fn default() -> Self {
+        Self {
+            items: Buffer::with_capacity_in(0, A::default()),
+            counters: Buffer::with_capacity_in(0, A::default()),
+            counter: N::ZERO,
+            epoch: 0,
+            cmp: C::default(),
+            stats: Stats::new(),
+        }
+    }
+}
+
+pub struct IntoIterSorted<T, C = MaxComparator, N = usize, A: Allocator = Global> {
+    state: IntoIterSortedState<T, C, N, A>,
+}
+
+enum IntoIterSortedState<T, C, N, A: Allocator> {
+    /// Items are popped from the heap until `next_back` is called
+    Heap(StableBinaryHeap<T, C, N, A>),
+    /// Remaining items sorted smallest first
+    Sorted(buffer::IntoIter<T, A>),
+    /// Only used while switching from `Heap` to `Sorted`
+    Empty,
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> Iterator for IntoIterSorted<T, C, N, A> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        match &mut self.state {
+            IntoIterSortedState::Heap(heap) => heap.pop(),
+            IntoIterSortedState::Sorted(iter) => iter.next_back(),
+            IntoIterSortedState::Empty => None,
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let exact = match &self.state {
+            IntoIterSortedState::Heap(heap) => heap.len(),
+            IntoIterSortedState::Sorted(iter) => iter.len(),
+            IntoIterSortedState::Empty => 0,
+        };
+        (exact, Some(exact))
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> DoubleEndedIterator
+    for IntoIterSorted<T, C, N, A>
+{
+    /// Returns the smallest remaining item. The first call sorts the
+    /// remaining items in O(n log n), later calls from either end are O(1)
+    fn next_back(&mut self) -> Option<T> {
+        if let IntoIterSortedState::Heap(heap) = &mut self.state {
+            heap.sort_ascending();
+            if let IntoIterSortedState::Heap(heap) =
+                mem::replace(&mut self.state, IntoIterSortedState::Empty)
+            {
+                self.state = IntoIterSortedState::Sorted(heap.items.into_iter());
+            }
+        }
+
+        match &mut self.state {
+            IntoIterSortedState::Sorted(iter) => iter.next(),
+            _ => None,
+        }
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> ExactSizeIterator for IntoIterSorted<T, C, N, A> {}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> FusedIterator for IntoIterSorted<T, C, N, A> {}
+
+pub struct IntoIterSortedAsc<T, C = MaxComparator, N = usize, A: Allocator = Global> {
+    inner: IntoIterSorted<T, Ascending<C>, N, A>,
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> Iterator for IntoIterSortedAsc<T, C, N, A> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> DoubleEndedIterator
+    for IntoIterSortedAsc<T, C, N, A>
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.inner.next_back()
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> ExactSizeIterator
+    for IntoIterSortedAsc<T, C, N, A>
+{
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> FusedIterator for IntoIterSortedAsc<T, C, N, A> {}
+
+pub struct IntoGroupsSorted<T, C = MaxComparator, N = usize, A: Allocator = Global> {
+    heap: StableBinaryHeap<T, C, N, A>,
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> Iterator for IntoGroupsSorted<T, C, N, A> {
+    type Item = (T, Vec<T>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(T, Vec<T>)> {
+        let mut group = self.heap.pop_tied_max().into_iter();
+        let first = group.next()?;
+        Some((first, group.collect()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.heap.len();
+        (len.min(1), Some(len))
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> FusedIterator for IntoGroupsSorted<T, C, N, A> {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::ToString, vec, vec::Vec};
+    use rand::{thread_rng, Rng};
+
+    use crate::test_util::{keyed_items, scatter, sorted_desc};
+    use crate::*;
+
+    fn generate_data(inp_len: usize) -> Vec<usize> {
+        let mut input = vec![0usize; inp_len];
+        thread_rng().try_fill(&mut input[..]).unwrap();
+        input
+    }
+
+    fn make_test(inp_len: usize) {
+        let input = generate_data(inp_len);
+
+        let mut expected = input.clone();
+        expected.sort_by(|a, b| a.cmp(b).reverse());
+
+        let mut stable_heap = StableBinaryHeap::new();
+        stable_heap.extend(input);
+
+        let out = stable_heap.into_iter_sorted().collect::<Vec<_>>();
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_heap_functionality() {
+        for inp_len in (1..9000).step_by(51) {
+            make_test(inp_len);
+        }
+    }
+
+    #[test]
+    fn test_stability_same() {
+        let mut heap = StableBinaryHeap::new();
+
+        for i in 0..1000 {
+            heap.push(UniqueItem::new(i, 0));
+        }
+
+        let vec = heap.into_sorted_vec();
+        for (i, item) in vec.iter().enumerate() {
+            assert_eq!(item.item, i);
+        }
+    }
+
+    #[test]
+    fn test_stability_simple() {
+        let mut heap = StableBinaryHeap::new();
+
+        heap.push(UniqueItem::new("9", 3));
+        heap.push(UniqueItem::new("8", 2));
+        heap.push(UniqueItem::new("7", 2));
+        heap.push(UniqueItem::new("a", 1));
+        heap.push(UniqueItem::new("b", 1));
+        heap.push(UniqueItem::new("c", 1));
+        heap.push(UniqueItem::new("d", 1));
+        heap.push(UniqueItem::new("e", 0));
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec!["9", "8", "7", "a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_stability_full() {
+        for inp_len in (1..10000).step_by(71) {
+            new_stability_test(inp_len);
+        }
+    }
+
+    #[test]
+    fn test_min_heap_functionality() {
+        let input = generate_data(2000);
+
+        let mut expected = input.clone();
+        expected.sort();
+
+        let mut heap = StableBinaryMinHeap::new_min();
+        heap.extend(input);
+
+        assert_eq!(heap.into_sorted_vec(), expected);
+    }
+
+    #[test]
+    fn test_min_heap_stability() {
+        let mut heap = StableBinaryMinHeap::new_min();
+
+        heap.push(UniqueItem::new("a", 1));
+        heap.push(UniqueItem::new("b", 0));
+        heap.push(UniqueItem::new("c", 1));
+        heap.push(UniqueItem::new("d", 0));
+        heap.push(UniqueItem::new("e", 2));
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec!["b", "d", "a", "c", "e"]);
+    }
+
+    #[test]
+    fn test_heap_by_stability() {
+        let mut heap = StableBinaryHeap::new_by(|a: &(&str, u32), b: &(&str, u32)| b.1.cmp(&a.1));
+
+        heap.push(("a", 2));
+        heap.push(("b", 1));
+        heap.push(("c", 2));
+        heap.push(("d", 1));
+        heap.push(("e", 0));
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.0).collect();
+        assert_eq!(out, vec!["e", "b", "d", "a", "c"]);
+    }
+
+    #[test]
+    fn test_heap_by_key_stability() {
+        let mut heap = StableBinaryHeap::new_by_key(|i: &UniqueItem<&str>| i.val);
+
+        heap.push(UniqueItem::new("a", 1));
+        heap.push(UniqueItem::new("b", 3));
+        heap.push(UniqueItem::new("c", 1));
+        heap.push(UniqueItem::new("d", 3));
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec!["b", "d", "a", "c"]);
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let heap: StableBinaryHeap<_> = (0..10).map(|i| UniqueItem::new(i, i % 3)).collect();
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec![2, 5, 8, 1, 4, 7, 0, 3, 6, 9]);
+
+        let heap: StableBinaryMinHeap<_> = (0..10).rev().collect();
+        assert_eq!(heap.into_sorted_vec(), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_from_vec() {
+        for inp_len in (0..2000).step_by(37) {
+            let input = generate_data(inp_len);
+
+            let mut expected = input.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+
+            let heap = StableBinaryHeap::from_vec(input);
+            assert_eq!(heap.counter(), inp_len);
+            assert_eq!(heap.into_sorted_vec(), expected);
+        }
+
+        let input: Vec<_> = (0..100).map(|i| UniqueItem::new(i, i % 7)).collect();
+        let mut expected = input.clone();
+        expected.sort_by_key(|i| std::cmp::Reverse(i.val));
+
+        let heap: StableBinaryHeap<_> = StableBinaryHeap::from(input);
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(
+            out,
+            expected.into_iter().map(|i| i.item).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_raw_parts() {
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, u32)| i.0);
+        heap.extend((0..50).map(|i: u32| (i % 4, i)));
+        heap.retain(|i| i.1 % 3 != 0);
+        let expected: Vec<_> = heap.clone().into_iter_sorted().collect();
+
+        let (mut items, counter) = heap.into_raw_parts();
+        assert_eq!(counter, 50);
+        items.reverse();
+        let cmp = KeyComparator(|i: &(u32, u32)| i.0);
+        let heap = StableBinaryHeap::from_raw_parts(items.clone(), counter, cmp).unwrap();
+        assert_eq!(heap.into_iter_sorted().collect::<Vec<_>>(), expected);
+
+        assert!(matches!(
+            StableBinaryHeap::from_raw_parts(items.clone(), 40, cmp),
+            Err(IntegrityError::CounterOutOfRange { .. })
+        ));
+        items[1].1 = items[0].1;
+        assert!(StableBinaryHeap::from_raw_parts(items, counter, cmp).is_err());
+    }
+
+    #[test]
+    fn test_meld() {
+        for (a_len, b_len) in [(3, 50), (50, 3), (0, 5), (5, 0)] {
+            let mut a = StableBinaryHeap::new();
+            let mut b = StableBinaryHeap::new();
+            for i in 0..a_len.max(b_len) {
+                if i < b_len {
+                    b.push(UniqueItem::new(100 + i, i % 3));
+                }
+                if i < a_len {
+                    a.push(UniqueItem::new(i, i % 3));
+                }
+            }
+
+            let mut expected: Vec<_> = (0..a_len).chain(100..100 + b_len).collect();
+            expected.sort_by_key(|i| std::cmp::Reverse(i % 100 % 3));
+
+            let heap = a.meld(b);
+            assert_eq!(heap.counter(), (a_len + b_len) as usize);
+            let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_append() {
+        let mut a = StableBinaryHeap::new();
+        let mut b = StableBinaryHeap::new();
+
+        a.push(UniqueItem::new("a1", 1));
+        b.push(UniqueItem::new("b1", 1));
+        b.push(UniqueItem::new("b2", 2));
+        a.push(UniqueItem::new("a2", 2));
+        a.push(UniqueItem::new("a3", 1));
+        b.push(UniqueItem::new("b3", 1));
+
+        a.append(&mut b);
+        assert!(b.is_empty());
+        assert_eq!(b.counter(), 0);
+        assert_eq!(a.counter(), 6);
+
+        a.push(UniqueItem::new("a4", 2));
+
+        let out: Vec<_> = a.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec!["a2", "b2", "a4", "a1", "a3", "b1", "b3"]);
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut heap: StableBinaryHeap<_> = (0..10).collect();
+
+        let mut out: Vec<_> = heap.drain().collect();
+        out.sort();
+
+        assert_eq!(out, (0..10).collect::<Vec<_>>());
+        assert!(heap.is_empty());
+        assert_eq!(heap.counter(), 0);
+    }
+
+    #[test]
+    fn test_drain_sorted() {
+        let mut heap = StableBinaryHeap::with_capacity(16);
+        heap.extend((0..10).map(|i| UniqueItem::new(i, i % 2)));
+
+        let out: Vec<_> = heap.drain_sorted().take(3).map(|i| i.item).collect();
+        assert_eq!(out, vec![1, 3, 5]);
+        assert!(heap.is_empty());
+        assert_eq!(heap.counter(), 0);
+        assert!(heap.capacity() >= 16);
+    }
+
+    #[test]
+    fn test_iter_sorted() {
+        let heap: StableBinaryHeap<_> = generate_data(1000)
+            .into_iter()
+            .map(|i| UniqueItem::new(i, (i % 13) as u32))
+            .collect();
+
+        let out: Vec<_> = heap.iter_sorted().map(|i| i.item).collect();
+        assert_eq!(heap.len(), 1000);
+
+        let expected: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    // Items in an `allocator_api2` buffer get moved into a global `Vec`
+    #[cfg(not(feature = "allocator-api2"))]
+    fn test_into_sorted_vec_in_place() {
+        let heap = StableBinaryHeap::from_vec(generate_data(500));
+        let ptr = heap.items.as_ptr() as usize;
+
+        let out = heap.into_sorted_vec();
+        assert_eq!(out.as_ptr() as usize, ptr);
+        assert!(out.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn test_peek_mut() {
+        let mut heap = StableBinaryHeap::new();
+        heap.push(UniqueItem::new("a", 3));
+        heap.push(UniqueItem::new("b", 2));
+        heap.push(UniqueItem::new("c", 1));
+
+        heap.peek_mut().unwrap().val = 1;
+        assert_eq!(heap.peek().unwrap().item, "b");
+        assert_eq!(heap.check_integrity(), Ok(()));
+
+        let top = StablePeekMut::pop(heap.peek_mut().unwrap());
+        assert_eq!(top.item, "b");
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec!["a", "c"]);
+    }
+
+    #[test]
+    fn test_push_with_handle() {
+        let mut heap = StableBinaryHeap::new();
+        let a = heap.push_with_handle(3);
+        let b = heap.push_with_handle(2);
+        let c = heap.push_with_handle(3);
+
+        assert!(a.seq() < b.seq() && b.seq() < c.seq());
+        assert_eq!(heap.get(b), Some(&2));
+
+        assert_eq!(heap.pop(), Some(3));
+        assert!(!heap.contains_handle(a));
+        assert!(heap.contains_handle(b));
+        assert!(heap.contains_handle(c));
+
+        // The new items get the counters of the cleared ones
+        heap.clear();
+        heap.push(42);
+        assert!(!heap.contains_handle(a));
+        assert_eq!(heap.remove(b), None);
+
+        let d = heap.push_with_handle(7);
+        heap.drain();
+        heap.extend([1, 2]);
+        assert_eq!(heap.get(d), None);
+    }
+
+    #[test]
+    fn test_update() {
+        let mut heap = StableBinaryHeap::new();
+        let handles: Vec<_> = (0..10)
+            .map(|i| heap.push_with_handle(UniqueItem::new(i, i)))
+            .collect();
+
+        assert!(heap.update(handles[2], |i| i.val = 5));
+        assert!(heap.update(handles[9], |i| i.val = 5));
+        assert!(heap.update(handles[7], |i| i.val = 1));
+        assert_eq!(heap.get(handles[7]).map(|i| i.val), Some(1));
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec![8, 6, 2, 5, 9, 4, 3, 1, 7, 0]);
+
+        let mut heap = StableBinaryHeap::new();
+        let a = heap.push_with_handle(1);
+        heap.pop();
+        assert!(!heap.update(a, |i| *i = 2));
+    }
+
+    #[test]
+    fn test_update_stale_handle() {
+        let mut heap: StableBinaryHeap<(u32, char), _, u8> =
+            StableBinaryHeap::from_comparator(MinComparator);
+        let a = heap.push_with_handle((5, 'a'));
+        heap.pop();
+        heap.push((7, 'b'));
+        for _ in 0..300 {
+            heap.push((1, 'x'));
+            assert_eq!(heap.pop(), Some((1, 'x')));
+        }
+
+        // Renumbering gave 'b' the counter of 'a', which must not be decreased
+        assert_eq!(heap.as_slices().1, [a.seq()]);
+        assert!(!heap.update(a, |i| i.0 = 0));
+        assert_eq!(heap.peek(), Some(&(7, 'b')));
+
+        let c = heap.push_with_handle((2, 'c'));
+        heap.clear();
+        heap.push((9, 'd'));
+        assert!(!heap.update(c, |i| i.0 = 0));
+        assert_eq!(heap.peek(), Some(&(9, 'd')));
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut heap = StableBinaryHeap::new();
+        assert_eq!(heap.push_pop(UniqueItem::new("a", 1)).item, "a");
+        assert!(heap.is_empty());
+
+        heap.push(UniqueItem::new("b", 2));
+        heap.push(UniqueItem::new("c", 1));
+
+        assert_eq!(heap.push_pop(UniqueItem::new("d", 3)).item, "d");
+        assert_eq!(heap.push_pop(UniqueItem::new("e", 2)).item, "b");
+        assert_eq!(heap.push_pop(UniqueItem::new("f", 1)).item, "e");
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec!["c", "f"]);
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut heap = StableBinaryHeap::new();
+        assert!(heap.replace(UniqueItem::new("a", 2)).is_none());
+
+        heap.push(UniqueItem::new("b", 1));
+        assert_eq!(heap.replace(UniqueItem::new("c", 1)).unwrap().item, "a");
+        assert_eq!(heap.replace(UniqueItem::new("d", 3)).unwrap().item, "b");
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec!["d", "c"]);
+    }
+
+    #[test]
+    fn test_pop_k() {
+        for k in [0, 1, 5, 100, 600, 1000, 2000] {
+            let input: Vec<_> = generate_data(1000)
+                .into_iter()
+                .map(|i| UniqueItem::new(i, (i % 17) as u32))
+                .collect();
+            let mut heap = StableBinaryHeap::from_vec(input);
+            let expected: Vec<_> = heap.iter_sorted().map(|i| i.item).collect();
+
+            let top: Vec<_> = heap.pop_k(k).into_iter().map(|i| i.item).collect();
+            assert_eq!(top, expected[..k.min(1000)]);
+
+            heap.push(UniqueItem::new(0, 0));
+            let rest: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+            assert_eq!(rest[..rest.len() - 1], expected[k.min(1000)..]);
+        }
+    }
+
+    #[test]
+    fn test_peek_k() {
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &u32| i % 7);
+        heap.extend((0..100).map(|i: u32| scatter(i, 100)));
+        let expected: Vec<_> = heap.iter_sorted().collect();
+
+        for k in [0, 1, 10, 100, 200] {
+            assert_eq!(heap.peek_k(k), expected[..k.min(100)]);
+        }
+        assert_eq!(heap.len(), 100);
+    }
+
+    #[test]
+    fn test_push_or_update() {
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(char, u32)| i.1);
+        for (id, prio) in [('a', 1), ('b', 2), ('c', 1), ('d', 3)] {
+            assert_eq!(heap.push_or_update((id, prio), |i| i.0 == id), None);
+        }
+
+        assert_eq!(
+            heap.push_or_update(('d', 1), |i| i.0 == 'd'),
+            Some(('d', 3))
+        );
+        assert_eq!(
+            heap.push_or_update(('a', 2), |i| i.0 == 'a'),
+            Some(('a', 1))
+        );
+        assert_eq!(heap.push_or_update(('e', 2), |i| i.0 == 'e'), None);
+        assert_eq!(heap.len(), 5);
+
+        let ids: Vec<_> = heap.into_iter_sorted().map(|i| i.0).collect();
+        assert_eq!(ids, ['a', 'b', 'e', 'c', 'd']);
+    }
+
+    #[test]
+    fn test_kth_largest() {
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &u32| i % 7);
+        heap.extend((0..100).map(|i: u32| scatter(i, 100)));
+        let expected: Vec<_> = heap.iter_sorted().collect();
+
+        for (k, item) in expected.into_iter().enumerate() {
+            assert_eq!(heap.kth_largest(k), Some(item));
+        }
+        assert_eq!(heap.kth_largest(100), None);
+    }
+
+    #[test]
+    fn test_count_ge() {
+        let heap: StableBinaryHeap<_> = (0..1000).map(|i: u32| scatter(i, 100)).collect();
+        for x in [0, 1, 50, 99, 100] {
+            assert_eq!(heap.count_ge(&x), heap.iter().filter(|i| **i >= x).count());
+        }
+        assert_eq!(StableBinaryHeap::<u32>::new().count_ge(&0), 0);
+    }
+
+    #[test]
+    fn test_drain_below() {
+        let items = keyed_items(300, 10);
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, u32)| i.0);
+        heap.extend(items.iter());
+
+        let mut removed = heap.drain_below(&(4, 0));
+        removed.sort_by_key(|i| i.1);
+        let expected: Vec<_> = items.iter().filter(|i| i.0 < 4).copied().collect();
+        assert_eq!(removed, expected);
+
+        let expected = sorted_desc(items.into_iter().filter(|i| i.0 >= 4));
+        assert_eq!(heap.into_iter_sorted().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_rebuild() {
+        use core::cell::Cell;
+
+        let keys: Vec<_> = (0..100u32).map(|i| Cell::new(i % 4)).collect();
+        let mut heap: StableBinaryHeap<_, _> =
+            StableBinaryHeap::new_by_key(|i: &usize| keys[*i].get());
+        heap.extend(0..keys.len());
+        heap.check_integrity().unwrap();
+
+        for key in &keys {
+            key.set(3 - key.get());
+        }
+        assert!(heap.check_integrity().is_err());
+        heap.rebuild();
+        heap.check_integrity().unwrap();
+
+        let mut expected: Vec<_> = (0..keys.len()).collect();
+        expected.sort_by_key(|i| std::cmp::Reverse(keys[*i].get()));
+        assert_eq!(heap.into_iter_sorted().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_iter_mut() {
+        let items = keyed_items(200, 10);
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, u32)| i.0);
+        heap.extend(items.iter());
+
+        // Age all items with an even key
+        for item in &mut heap.iter_mut() {
+            if item.0 % 2 == 0 {
+                item.0 += 5;
+            }
+        }
+        heap.check_integrity().unwrap();
+
+        let expected =
+            sorted_desc(
+                items
+                    .into_iter()
+                    .map(|(k, v)| if k % 2 == 0 { (k + 5, v) } else { (k, v) }),
+            );
+        assert_eq!(heap.into_iter_sorted().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_extract_if() {
+        let items = keyed_items(300, 10);
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, u32)| i.0);
+        heap.extend(items.iter());
+
+        let mut extracted: Vec<_> = heap.extract_if(|i| i.1 % 3 == 0).collect();
+        extracted.sort_by_key(|i| i.1);
+        let expected: Vec<_> = items.iter().filter(|i| i.1 % 3 == 0).copied().collect();
+        assert_eq!(extracted, expected);
+
+        // Dropping the iterator early keeps the unvisited items
+        assert!(heap.extract_if(|i| i.1 % 3 == 1).next().is_some());
+        assert_eq!(heap.len(), 199);
+        heap.check_integrity().unwrap();
+
+        let expected = sorted_desc(items.into_iter().filter(|i| i.1 % 3 == 2));
+        heap.retain(|i| i.1 % 3 == 2);
+        assert_eq!(heap.into_iter_sorted().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_pop_while() {
+        let mut heap: StableBinaryHeap<_> = (0..20).collect();
+        let popped: Vec<_> = heap.pop_while(|i| *i >= 15).collect();
+        assert_eq!(popped, [19, 18, 17, 16, 15]);
+
+        // Items not consumed stay in the heap
+        assert_eq!(heap.pop_while(|i| i % 2 == 0).next(), Some(14));
+        assert_eq!(heap.peek(), Some(&13));
+        assert_eq!(heap.pop_while(|_| true).count(), 14);
+    }
+
+    #[test]
+    fn test_pop_if() {
+        let mut heap: StableBinaryHeap<_> = [3, 1, 2].into_iter().collect();
+        assert_eq!(heap.pop_if(|i| *i > 3), None);
+        assert_eq!(heap.pop_if(|i| *i == 3), Some(3));
+        assert_eq!(heap.len(), 2);
+        heap.clear();
+        assert_eq!(heap.pop_if(|_| true), None);
+    }
+
+    #[test]
+    fn test_pop_tied_max() {
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &u32| i % 20);
+        heap.extend(0..100);
+        for key in (0..20).rev() {
+            let expected: Vec<_> = (0..100).filter(|i| i % 20 == key).collect();
+            assert_eq!(heap.pop_tied_max(), expected);
+        }
+        assert!(heap.pop_tied_max().is_empty());
+
+        // Large groups get selected in O(n)
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &u32| i / 90);
+        heap.extend((0..100).rev());
+        assert_eq!(heap.pop_tied_max(), (90..100).rev().collect::<Vec<_>>());
+        assert_eq!(heap.pop_tied_max(), (0..90).rev().collect::<Vec<_>>());
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_into_groups_sorted() {
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, char)| i.0);
+        heap.extend([(1, 'a'), (3, 'b'), (1, 'c'), (3, 'd'), (2, 'e'), (3, 'f')]);
+
+        let mut groups = heap.into_groups_sorted();
+        assert_eq!(groups.size_hint(), (1, Some(6)));
+        assert_eq!(groups.next(), Some(((3, 'b'), vec![(3, 'd'), (3, 'f')])));
+        assert_eq!(groups.next(), Some(((2, 'e'), vec![])));
+        assert_eq!(groups.next(), Some(((1, 'a'), vec![(1, 'c')])));
+        assert_eq!(groups.next(), None);
+    }
+
+    #[test]
+    fn test_split_off_top() {
+        let mut expected: Vec<u32> = (0..100).collect();
+        expected.sort_by_key(|i| std::cmp::Reverse(i % 7));
+
+        for k in [0, 3, 40, 100, 200] {
+            let mut heap = StableBinaryHeap::new();
+            let handles: Vec<_> = (0..100)
+                .map(|i| heap.push_with_handle(UniqueItem::new(i, i % 7)))
+                .collect();
+
+            let top = heap.split_off_top(k);
+            assert_eq!(top.len(), k.min(100));
+            assert_eq!(top.counter(), heap.counter());
+            assert_eq!(top.contains_handle(handles[expected[0] as usize]), k > 0);
+
+            let mut out: Vec<_> = top.into_iter_sorted().map(|i| i.item).collect();
+            out.extend(heap.into_iter_sorted().map(|i| i.item));
+            assert_eq!(out, expected);
+        }
+
+        let mut heap = StableBinaryHeap::new();
+        heap.extend([(1, 'a'), (1, 'b'), (0, 'c')].map(|(v, i)| UniqueItem::new(i, v)));
+        let mut top = heap.split_off_top(1);
+        top.push(UniqueItem::new('d', 1));
+        heap.push(UniqueItem::new('e', 1));
+        assert_eq!(
+            top.into_sorted_vec()
+                .iter()
+                .map(|i| i.item)
+                .collect::<Vec<_>>(),
+            ['a', 'd']
+        );
+        assert_eq!(
+            heap.into_sorted_vec()
+                .iter()
+                .map(|i| i.item)
+                .collect::<Vec<_>>(),
+            ['b', 'e', 'c']
+        );
+    }
+
+    #[test]
+    fn test_split_off_top_counter() {
+        let mut heap: StableBinaryHeap<_, _, u8> =
+            StableBinaryHeap::from_comparator(KeyComparator(|i: &(u32, u32)| i.0));
+        for i in 0..250 {
+            heap.push((i % 2, i));
+            if i >= 4 {
+                heap.pop();
+            }
+        }
+        let mut top = heap.split_off_top(2);
+        assert_eq!(top.counter(), 250);
+
+        // Items pushed into the split heap rank behind the moved ones, also
+        // once the counter overflows and gets renumbered
+        for i in 250..260 {
+            top.push((0, i));
+        }
+        let out: Vec<_> = top.into_iter_sorted().map(|i| i.1).collect();
+        assert_eq!(
+            out,
+            [242, 244, 250, 251, 252, 253, 254, 255, 256, 257, 258, 259]
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut expected: Vec<u32> = (0..100).collect();
+        expected.sort_by_key(|i| std::cmp::Reverse(i % 7));
+
+        for k in [0, 1, 20, 99, 100, 150] {
+            let mut heap = StableBinaryHeap::new();
+            heap.extend((0..100).map(|i| UniqueItem::new(i, i % 7)));
+
+            heap.truncate(k);
+            assert_eq!(heap.len(), k.min(100));
+            assert_eq!(heap.counter(), 100);
+
+            let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+            assert_eq!(out, expected[..k.min(100)]);
+        }
+    }
+
+    #[test]
+    fn test_into_iter_ref() {
+        let heap: StableBinaryHeap<_> = (0..10).collect();
+
+        let mut sum = 0;
+        for i in &heap {
+            sum += *i;
+        }
+        assert_eq!(sum, 45);
+
+        let iter = heap.iter();
+        assert_eq!(iter.size_hint(), (10, Some(10)));
+        assert_eq!(iter.clone().max(), Some(&9));
+    }
+
+    #[test]
+    fn test_as_slices() {
+        let mut heap = StableBinaryHeap::new();
+        heap.extend([3, 1, 3, 2]);
+        heap.pop();
+
+        let (items, counters) = heap.as_slices();
+        assert_eq!(items.len(), 3);
+        assert!(items.iter().eq(heap.iter()));
+
+        let mut counters = counters.to_vec();
+        counters.sort();
+        assert_eq!(counters, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_into_vec_insertion_order() {
+        let items: Vec<_> = (0..100u32).map(|i| scatter(i, 17)).collect();
+        let mut heap = StableBinaryHeap::new();
+        heap.extend(items.iter().copied());
+        heap.pop();
+        heap.push(100);
+
+        let mut expected = items;
+        let max = expected.iter().position(|i| *i == 16).unwrap();
+        expected.remove(max);
+        expected.push(100);
+        assert_eq!(heap.into_vec_insertion_order(), expected);
+    }
+
+    #[test]
+    fn test_lifo() {
+        use compare::{Fifo, Lifo};
+
+        let items = [(1, 'a'), (2, 'b'), (1, 'c'), (2, 'd'), (2, 'e')];
+        let key = |i: &(u32, char)| i.0;
+
+        let mut heap: StableBinaryHeap<_, _> =
+            StableBinaryHeap::from_comparator(Lifo(KeyComparator(key)));
+        heap.extend(items);
+        let popped: Vec<_> = heap.into_iter_sorted().map(|i| i.1).collect();
+        assert_eq!(popped, ['e', 'd', 'b', 'c', 'a']);
+
+        let mut heap: StableBinaryHeap<_, Lifo> = StableBinaryHeap::default();
+        heap.extend(items);
+        assert_eq!(heap.pop(), Some((2, 'e')));
+
+        let mut heap: StableBinaryHeap<_, _> =
+            StableBinaryHeap::from_comparator(Fifo(KeyComparator(key)));
+        heap.extend(items);
+        let popped: Vec<_> = heap.into_iter_sorted().map(|i| i.1).collect();
+        assert_eq!(popped, ['b', 'd', 'e', 'a', 'c']);
+    }
+
+    #[test]
+    fn test_lifo_wrapped() {
+        use compare::{Lifo, ThenComparator};
+
+        // (score, id, arrival): higher score first, then higher id, then newest
+        let items = [
+            (1, 2, 'a'),
+            (2, 1, 'b'),
+            (1, 2, 'c'),
+            (2, 1, 'd'),
+            (2, 3, 'e'),
+        ];
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::from_comparator(ThenComparator(
+            Lifo(KeyComparator(|i: &(u32, u32, char)| i.0)),
+            KeyComparator(|i: &(u32, u32, char)| i.1),
+        ));
+        heap.extend(items);
+        let popped: Vec<_> = heap.clone().into_iter_sorted().map(|i| i.2).collect();
+        assert_eq!(popped, ['e', 'd', 'b', 'c', 'a']);
+
+        let asc: Vec<_> = heap
+            .into_sorted_vec_asc()
+            .into_iter()
+            .map(|i| i.2)
+            .collect();
+        assert_eq!(asc, ['c', 'a', 'd', 'b', 'e']);
+    }
+
+    #[test]
+    fn test_then_comparator() {
+        use compare::ThenComparator;
+
+        // (score, id, arrival): higher score first, then lower id, then arrival
+        let items = [
+            (1, 5, 'a'),
+            (2, 7, 'b'),
+            (2, 3, 'c'),
+            (1, 5, 'd'),
+            (2, 7, 'e'),
+        ];
+        let cmp = ThenComparator(
+            KeyComparator(|i: &(u32, u32, char)| i.0),
+            KeyComparator(|i: &(u32, u32, char)| std::cmp::Reverse(i.1)),
+        );
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::from_comparator(cmp);
+        heap.extend(items);
+
+        let popped: Vec<_> = heap.into_iter_sorted().map(|i| i.2).collect();
+        assert_eq!(popped, ['c', 'b', 'e', 'a', 'd']);
+    }
+
+    #[test]
+    fn test_peek_with_seq() {
+        let mut heap = StableBinaryHeap::new();
+        assert_eq!(heap.peek_with_seq(), None);
+
+        heap.extend([1, 3, 2, 3]);
+        assert_eq!(heap.peek_with_seq(), Some((&3, 1)));
+        heap.pop();
+        assert_eq!(heap.peek_with_seq(), Some((&3, 3)));
+    }
+
+    #[test]
+    fn test_modify_top() {
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, char)| i.0);
+        assert_eq!(heap.modify_top(|i| i.0), None);
+
+        heap.extend([(5, 'a'), (3, 'b'), (4, 'c'), (3, 'd')]);
+        let old = heap.modify_top(|i| mem::replace(&mut i.0, 3));
+        assert_eq!(old, Some(5));
+        assert_eq!(heap.peek(), Some(&(4, 'c')));
+
+        heap.modify_top(|i| i.0 = 1);
+        let popped: Vec<_> = heap.into_iter_sorted().collect();
+        assert_eq!(popped, [(3, 'a'), (3, 'b'), (3, 'd'), (1, 'c')]);
+    }
+
+    #[test]
+    fn test_remove_handle() {
+        let items = keyed_items(50, 7);
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, u32)| i.0);
+        let handles: Vec<_> = items.iter().map(|i| heap.push_with_handle(*i)).collect();
+
+        for i in (0..50).step_by(3) {
+            assert_eq!(heap.remove(handles[i]), Some(items[i]));
+            assert_eq!(heap.remove(handles[i]), None);
+        }
+
+        let expected = sorted_desc(items.into_iter().filter(|i| i.1 % 3 != 0));
+        assert_eq!(heap.into_sorted_vec(), expected);
+    }
+
+    #[test]
+    fn test_extend_bulk() {
+        let items = keyed_items(500, 13);
+
+        // Small and large batches relative to the heap size
+        for split in [0, 10, 250, 490, 500] {
+            let mut heap: StableBinaryHeap<_, _> =
+                StableBinaryHeap::new_by_key(|i: &(u32, u32)| i.0);
+            heap.extend(items[..split].iter().copied());
+            heap.extend(items[split..].iter().copied());
+
+            let expected = sorted_desc(items.iter().copied());
+            assert_eq!(heap.into_sorted_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn test_extend_reserve() {
+        let mut heap = StableBinaryHeap::new();
+        heap.extend(0..1000);
+        assert_eq!(heap.capacity(), 1000);
+
+        heap.extend((0..10).filter(|i| i % 2 == 0));
+        assert_eq!(heap.len(), 1005);
+    }
+
+    #[test]
+    fn test_extend_ref() {
+        let items = [(1, 'a'), (3, 'b'), (1, 'c'), (3, 'd')];
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, char)| i.0);
+        heap.extend(items.iter());
+        heap.extend(&[(2, 'e')]);
+        let sorted: Vec<_> = heap.into_iter_sorted().map(|i| i.1).collect();
+        assert_eq!(sorted, ['b', 'd', 'e', 'a', 'c']);
+    }
+
+    #[test]
+    fn test_float_priority() {
+        let items = [
+            (0.5, 'a'),
+            (f64::NAN, 'b'),
+            (-1.0, 'c'),
+            (0.5, 'd'),
+            (2.0, 'e'),
+        ];
+        let mut heap: StableBinaryHeap<_, _> =
+            StableBinaryHeap::new_by_key(|i: &(f64, char)| TotalF64(i.0));
+        heap.extend(items);
+
+        let popped: Vec<_> = heap.into_iter_sorted().map(|i| i.1).collect();
+        assert_eq!(popped, ['b', 'e', 'a', 'd', 'c']);
+
+        let mut heap = StableBinaryHeap::new_min();
+        heap.extend([1.5f32, -0.0, 0.0, -2.0].map(TotalF32));
+        assert_eq!(heap.pop(), Some(TotalF32(-2.0)));
+        assert_eq!(heap.pop().map(|i| i.0.to_bits()), Some((-0.0f32).to_bits()));
+    }
+
+    #[test]
+    fn test_direction_markers() {
+        use compare::{Max, Min};
+
+        fn drain_all<C: Compare<(u32, char)> + Default>(items: &[(u32, char)]) -> Vec<char> {
+            let mut heap: StableBinaryHeap<_, C> = StableBinaryHeap::default();
+            heap.extend(items.iter().map(|i| (i.0, 'x')));
+            heap.extend(items.iter().copied());
+            heap.into_iter_sorted().map(|i| i.1).collect()
+        }
+
+        let items = [(2, 'a'), (1, 'b'), (3, 'c')];
+        assert_eq!(drain_all::<Max>(&items), ['x', 'c', 'x', 'a', 'x', 'b']);
+        assert_eq!(drain_all::<Min>(&items), ['b', 'x', 'a', 'x', 'c', 'x']);
+
+        let _: StableBinaryMinHeap<u32> = StableBinaryHeap::<u32, Min>::new_min();
+    }
+
+    #[test]
+    fn test_into_iter_sorted_rev() {
+        let items = keyed_items(200, 13);
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, u32)| i.0);
+        heap.extend(items);
+
+        let sorted = heap.clone().into_sorted_vec();
+        let rev: Vec<_> = heap.clone().into_iter_sorted().rev().collect();
+        assert!(sorted.iter().rev().eq(rev.iter()));
+
+        let mut iter = heap.into_iter_sorted();
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        for n in 0.. {
+            let item = if n % 3 == 0 {
+                iter.next_back().map(|i| back.push(i))
+            } else {
+                iter.next().map(|i| front.push(i))
+            };
+            if item.is_none() {
+                break;
+            }
+            assert_eq!(iter.len(), 200 - front.len() - back.len());
+        }
+        front.extend(back.into_iter().rev());
+        assert_eq!(front, sorted);
+    }
+
+    #[test]
+    fn test_into_sorted_asc() {
+        let items = keyed_items(200, 13);
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, u32)| i.0);
+        heap.extend(items.iter().copied());
+
+        let mut expected = items;
+        expected.sort_by_key(|i| i.0);
+        assert_eq!(heap.clone().into_sorted_vec_asc(), expected);
+        assert_eq!(heap.into_iter_sorted_asc().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_try_reserve() {
+        let mut heap = StableBinaryHeap::<u32>::new();
+        assert!(heap.try_reserve(10).is_ok());
+        assert!(heap.capacity() >= 10);
+        assert!(heap.try_reserve_exact(20).is_ok());
+        assert!(heap.capacity() >= 20);
+        assert!(heap.try_reserve(usize::MAX).is_err());
+        assert!(heap.try_reserve_exact(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_const_new() {
+        static HEAP: std::sync::Mutex<StableBinaryHeap<u32>> =
+            std::sync::Mutex::new(StableBinaryHeap::new());
+        const MIN: StableBinaryHeap<u32, MinComparator> = StableBinaryHeap::new_min();
+
+        HEAP.lock().unwrap().extend([2, 3, 1]);
+        assert_eq!(HEAP.lock().unwrap().pop(), Some(3));
+        assert!(MIN.is_empty());
+    }
+
+    #[test]
+    fn test_binary_heap_conversion() {
+        let items: Vec<_> = (0..100u32).map(|i| scatter(i, 17)).collect();
+        let binary_heap = std::collections::BinaryHeap::from(items.clone());
+        let order = binary_heap.clone().into_vec();
+
+        let heap: StableBinaryHeap<_> = StableBinaryHeap::from(binary_heap);
+        assert_eq!(
+            heap.clone().into_sorted_vec(),
+            StableBinaryHeap::from_vec(order).into_sorted_vec()
+        );
+
+        let mut expected = items;
+        expected.sort();
+        assert_eq!(heap.into_binary_heap().into_sorted_vec(), expected);
+    }
+
+    #[test]
+    fn test_exact_size() {
+        let mut heap: StableBinaryHeap<_> = (0..10).collect();
+        assert_eq!(heap.iter().len(), 10);
+        assert_eq!(heap.iter_sorted().len(), 10);
+
+        let mut sorted = heap.clone().into_iter_sorted();
+        sorted.next();
+        assert_eq!(sorted.len(), 9);
+
+        let mut drain = heap.drain_sorted();
+        drain.next();
+        assert_eq!(drain.len(), 9);
+        drop(drain);
+
+        heap.extend(0..5);
+        let mut drain = heap.drain();
+        drain.next();
+        assert_eq!(drain.len(), 4);
+    }
+
+    #[test]
+    fn test_bounded_heap() {
+        let mut heap = StableBoundedHeap::new(3);
+        assert!(heap.push(UniqueItem::new("a", 1)).is_none());
+        assert!(heap.push(UniqueItem::new("b", 2)).is_none());
+        assert!(heap.push(UniqueItem::new("c", 1)).is_none());
+        assert!(heap.is_full());
+
+        let evicted = |i: Option<UniqueItem<&'static str>>| i.unwrap().item;
+        assert_eq!(evicted(heap.push(UniqueItem::new("d", 1))), "d");
+        assert_eq!(evicted(heap.push(UniqueItem::new("e", 2))), "c");
+        assert_eq!(heap.peek_min().unwrap().item, "a");
+
+        assert_eq!(evicted(heap.push(UniqueItem::new("f", 3))), "a");
+        assert_eq!(heap.len(), 3);
+
+        let out: Vec<_> = heap.into_sorted_vec().into_iter().map(|i| i.item).collect();
+        assert_eq!(out, vec!["f", "b", "e"]);
+    }
+
+    #[test]
+    fn test_bounded_heap_random() {
+        for bound in [0, 1, 10, 500, 2000] {
+            let input: Vec<_> = generate_data(1000)
+                .into_iter()
+                .map(|i| UniqueItem::new(i, (i % 31) as u32))
+                .collect();
+
+            let mut bounded = StableBoundedHeap::new(bound);
+            bounded.extend(input.clone());
+
+            let expected: Vec<_> = StableBinaryHeap::from_vec(input)
+                .into_iter_sorted()
+                .take(bound)
+                .map(|i| i.item)
+                .collect();
+            let out: Vec<_> = bounded
+                .into_sorted_vec()
+                .into_iter()
+                .map(|i| i.item)
+                .collect();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut heap = StableBinaryHeap::new();
+        for i in 0..=5 {
+            heap.push(i);
+        }
+
+        heap.retain(|i| *i != 2);
+
+        assert_eq!(heap.into_sorted_vec(), vec![5, 4, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_retain_mut() {
+        let mut heap = StableBinaryHeap::new();
+        for i in 0..10 {
+            heap.push(UniqueItem::new(i, i));
+        }
+
+        heap.retain_mut(|i| {
+            i.val = i.val.saturating_sub(5) % 2;
+            i.item % 4 != 0
+        });
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, vec![6, 1, 2, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn test_remove_first() {
+        let input: Vec<_> = generate_data(1000)
+            .into_iter()
+            .map(|i| UniqueItem::new(i, (i % 23) as u32))
+            .collect();
+
+        let mut heap = StableBinaryHeap::from_vec(input.clone());
+        let mut expected: Vec<_> = heap.iter_sorted().map(|i| i.item).collect();
+
+        for item in input.iter().step_by(7) {
+            let removed = heap.remove_first(|i| i.item == item.item).unwrap();
+            assert_eq!(removed.item, item.item);
+            expected.retain(|i| *i != item.item);
+        }
+
+        assert!(heap.remove_first(|i| i.val > 23).is_none());
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_contains_find() {
+        let heap: StableBinaryHeap<_> = (0..10).map(|i| i * 2).collect();
+
+        assert!(heap.contains(&8));
+        assert!(!heap.contains(&9));
+
+        assert_eq!(heap.find(|i| *i > 10 && *i < 14), Some(&12));
+        assert_eq!(heap.find(|i| *i > 20), None);
+    }
+
+    #[test]
+    fn test_renormalize() {
+        let mut heap = StableBinaryHeap::new();
+        for i in 0..20 {
+            heap.push(UniqueItem::new(i, i % 3));
+        }
+        heap.retain(|i| i.item % 2 == 0);
+
+        heap.counter = usize::MAX - 2;
+        for i in 20..30 {
+            heap.push(UniqueItem::new(i, i % 3));
+        }
+        assert!(heap.counter() < 30);
+
+        let mut expected: Vec<_> = (0..30).filter(|i| *i >= 20 || i % 2 == 0).collect();
+        expected.sort_by_key(|i| std::cmp::Reverse(i % 3));
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_counter_width() {
+        let mut heap = StableBinaryHeap::<_, MaxComparator, u8>::default();
+        let mut expected = vec![];
+
+        for i in 0..1000 {
+            heap.push(UniqueItem::new(i, i % 3));
+            if heap.len() > 50 {
+                expected.push(heap.pop().unwrap().item);
+            }
+        }
+        expected.extend(heap.into_iter_sorted().map(|i| i.item));
+
+        let mut wide = StableBinaryHeap::<_, MaxComparator, u128>::default();
+        let mut wide_expected = vec![];
+        for i in 0..1000 {
+            wide.push(UniqueItem::new(i, i % 3));
+            if wide.len() > 50 {
+                wide_expected.push(wide.pop().unwrap().item);
+            }
+        }
+        wide_expected.extend(wide.into_iter_sorted().map(|i| i.item));
+
+        assert_eq!(expected, wide_expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_counter_width_too_small() {
+        let _: StableBinaryHeap<_, MaxComparator, u8> = (0..1000).collect();
+    }
+
+    #[test]
+    #[should_panic(expected = "too many items for the heap's counter type")]
+    fn test_counter_full_after_renormalize() {
+        let mut heap: StableBinaryHeap<_, MaxComparator, u8> =
+            StableBinaryHeap::from_comparator(MaxComparator);
+        for i in 0..300 {
+            heap.push(i);
+        }
+    }
+
+    #[test]
+    fn test_handle_after_renormalize() {
+        let mut heap: StableBinaryHeap<u32, MaxComparator, u8> =
+            StableBinaryHeap::from_comparator(MaxComparator);
+        let popped = heap.push_with_handle(1000);
+        let kept = heap.push_with_handle(0);
+        assert_eq!(heap.pop(), Some(1000));
+        for i in 1..300 {
+            heap.push(i);
+            assert_eq!(heap.pop(), Some(i));
+        }
+
+        // The counter overflowed and the remaining item got renumbered to the
+        // counter of the popped one
+        assert_eq!(heap.as_slices().1, [0]);
+        assert!(!heap.contains_handle(popped));
+        assert_eq!(heap.get(popped), None);
+        assert!(!heap.contains_handle(kept));
+
+        let fresh = heap.push_with_handle(5);
+        assert_eq!(heap.get(fresh), Some(&5));
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut heap = StableBinaryHeap::new();
+        for i in 0..100 {
+            heap.push(UniqueItem::new(i, i % 5));
+        }
+
+        let mut clone = heap.clone();
+        assert_eq!(clone.counter(), heap.counter());
+
+        heap.push(UniqueItem::new(100, 2));
+        clone.push(UniqueItem::new(100, 2));
+
+        let a: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        let b: Vec<_> = clone.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut heap = StableBinaryHeap::new();
+        heap.push(1);
+        heap.push(2);
+
+        assert_eq!(
+            format!("{heap:?}"),
+            "StableBinaryHeap { items: [2, 1], counters: [1, 0], counter: 2 }"
+        );
+    }
+
+    #[test]
+    fn test_eq() {
+        let mut a = StableBinaryHeap::new();
+        let mut b = StableBinaryHeap::new();
+
+        a.push((1, 'x'));
+        a.push((3, 'y'));
+        a.push((2, 'z'));
+
+        b.push((0, 'w'));
+        b.push((2, 'z'));
+        b.push((3, 'y'));
+        b.push((1, 'x'));
+        assert_ne!(a, b);
+
+        b.retain(|i| i.0 != 0);
+        assert_eq!(a, b);
+
+        let by_key = |i: &(u32, char)| i.0 / 2;
+        let mut c = StableBinaryHeap::new_by_key(by_key);
+        let mut d = StableBinaryHeap::new_by_key(by_key);
+        c.extend([(2, 'a'), (3, 'b')]);
+        d.extend([(3, 'b'), (2, 'a')]);
+        assert!(c != d);
+    }
+
+    #[test]
+    fn test_retain_stability() {
+        let mut heap = StableBinaryHeap::new();
+        for i in 0..100 {
+            heap.push(UniqueItem::new(i, i % 4));
+        }
+
+        heap.retain(|i| i.item % 3 != 0);
+        assert_eq!(heap.counter(), 100);
+
+        let mut expected: Vec<_> = (0..100).filter(|i| i % 3 != 0).collect();
+        expected.sort_by_key(|i| std::cmp::Reverse(i % 4));
+
+        let out: Vec<_> = heap.into_iter_sorted().map(|i| i.item).collect();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_allocator() {
+        let mut heap = StableBinaryHeap::with_capacity_in(10, Global);
+        heap.extend([(1, 'a'), (2, 'b'), (1, 'c')]);
+        assert!(heap.capacity() >= 10);
+
+        let _: &Global = heap.allocator();
+        assert_eq!(heap.into_sorted_vec(), [(2, 'b'), (1, 'c'), (1, 'a')]);
+    }
+
+    #[test]
+    #[cfg(feature = "allocator-api2")]
+    fn test_custom_allocator() {
+        use allocator_api2::alloc::{AllocError, Allocator};
+        use core::{alloc::Layout, cell::Cell, ptr::NonNull};
+
+        #[derive(Clone)]
+        struct CountingAlloc<'a>(&'a Cell<usize>);
+
+        unsafe impl Allocator for CountingAlloc<'_> {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.0.set(self.0.get() + 1);
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                Global.deallocate(ptr, layout)
+            }
+        }
+
+        let allocations = Cell::new(0);
+        let mut heap = StableBinaryHeap::new_in(CountingAlloc(&allocations));
+        for i in 0..100 {
+            heap.push(UniqueItem::new(i, i % 3));
+        }
+        assert!(allocations.get() > 0);
+
+        let out: Vec<_> = heap.drain_sorted().map(|i| i.item).collect();
+        let mut expected: Vec<_> = (0..100).collect();
+        expected.sort_by_key(|i| std::cmp::Reverse(i % 3));
+        assert_eq!(out, expected);
     }
 
     fn new_stability_test(inp_len: usize) {
@@ -315,7 +3188,7 @@ mod tests {
                 assert_eq!(last.item, prev_namae);
             }
 
-            last = &i;
+            last = i;
         }
     }
 
@@ -341,7 +3214,7 @@ mod tests {
 
     impl<T> PartialOrd for UniqueItem<T> {
         fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-            self.val.partial_cmp(&other.val)
+            Some(self.cmp(other))
         }
     }
 
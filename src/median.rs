@@ -0,0 +1,194 @@
+use crate::{compare::MinComparator, StableBinaryHeap};
+use core::fmt::{self, Debug};
+
+/// Keeps track of the median of all pushed items. `push` runs in O(log n),
+/// `median` in O(1).
+///
+/// Equal items are ordered by insertion, the older item being the smaller one,
+/// so for an even amount of items the two middle items are always well defined
+#[derive(Clone)]
+pub struct StableMedianHeap<T> {
+    /// The smaller half of the items, including the lower median
+    lower: StableBinaryHeap<(T, u64)>,
+    /// The greater half of the items
+    upper: StableBinaryHeap<(T, u64), MinComparator>,
+    seq: u64,
+}
+
+impl<T: Ord> StableMedianHeap<T> {
+    /// Creates a new empty median heap
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            lower: StableBinaryHeap::new(),
+            upper: StableBinaryHeap::new_min(),
+            seq: 0,
+        }
+    }
+
+    /// Pushes a new item
+    pub fn push(&mut self, item: T) {
+        let entry = (item, self.seq);
+        self.seq += 1;
+
+        match self.lower.peek() {
+            Some(lower) if entry > *lower => self.upper.push(entry),
+            _ => self.lower.push(entry),
+        }
+        self.rebalance();
+    }
+
+    /// Returns the median. For an even amount of items this is the lower of the
+    /// two middle items
+    #[inline]
+    pub fn median(&self) -> Option<&T> {
+        self.lower.peek().map(|i| &i.0)
+    }
+
+    /// Returns the two middle items, lower first. Both are the same item if the
+    /// amount of items is odd
+    pub fn medians(&self) -> Option<(&T, &T)> {
+        let lower = self.median()?;
+        if self.lower.len() > self.upper.len() {
+            return Some((lower, lower));
+        }
+        self.upper.peek().map(|upper| (lower, &upper.0))
+    }
+
+    /// Removes the median returned by `median` and returns it
+    pub fn pop_median(&mut self) -> Option<T> {
+        let (item, _) = self.lower.pop()?;
+        self.rebalance();
+        Some(item)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lower.len() + self.upper.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lower.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.lower.clear();
+        self.upper.clear();
+        self.seq = 0;
+    }
+
+    /// Returns an iterator over all items in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.lower.iter().chain(self.upper.iter()).map(|i| &i.0)
+    }
+
+    /// Moves items between the halves until `lower` holds as many items as
+    /// `upper` or one more
+    fn rebalance(&mut self) {
+        if self.lower.len() > self.upper.len() + 1 {
+            if let Some(item) = self.lower.pop() {
+                self.upper.push(item);
+            }
+        } else if self.upper.len() > self.lower.len() {
+            if let Some(item) = self.upper.pop() {
+                self.lower.push(item);
+            }
+        }
+    }
+}
+
+impl<T: Debug> Debug for StableMedianHeap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableMedianHeap")
+            .field("lower", &self.lower)
+            .field("upper", &self.upper)
+            .finish()
+    }
+}
+
+impl<T: Ord> Default for StableMedianHeap<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> Extend<T> for StableMedianHeap<T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for i in iter {
+            self.push(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableMedianHeap;
+    use crate::test_util::scatter;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_median() {
+        let mut heap = StableMedianHeap::new();
+        assert_eq!(heap.median(), None);
+        assert_eq!(heap.medians(), None);
+
+        let mut items = Vec::new();
+        for i in 0..200u32 {
+            let item = scatter(i, 37);
+            heap.push(item);
+            items.push(item);
+            items.sort();
+
+            let lower = &items[(items.len() - 1) / 2];
+            let upper = &items[items.len() / 2];
+            assert_eq!(heap.median(), Some(lower));
+            assert_eq!(heap.medians(), Some((lower, upper)));
+        }
+
+        while let Some(median) = heap.pop_median() {
+            assert_eq!(median, items.remove((items.len() - 1) / 2));
+        }
+        assert!(items.is_empty());
+    }
+
+    #[test]
+    fn test_median_ties() {
+        let mut heap = StableMedianHeap::new();
+        heap.extend([(1, 'a'), (2, 'b'), (1, 'c'), (2, 'd')].map(|i| Key(i.0, i.1)));
+        assert_eq!(heap.medians().map(|(a, b)| (a.1, b.1)), Some(('c', 'b')));
+
+        heap.push(Key(2, 'e'));
+        assert_eq!(heap.median().map(|i| i.1), Some('b'));
+        assert_eq!(heap.pop_median().map(|i| i.1), Some('b'));
+        assert_eq!(heap.medians().map(|(a, b)| (a.1, b.1)), Some(('c', 'd')));
+    }
+
+    /// Compares only by the first field
+    #[derive(Debug)]
+    struct Key(u32, char);
+
+    impl PartialEq for Key {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Key {}
+
+    impl PartialOrd for Key {
+        fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Key {
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+}
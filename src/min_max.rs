@@ -0,0 +1,305 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    counter::{self, Counter},
+    item::HeapItem,
+};
+use alloc::vec::Vec;
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+};
+
+/// Double-ended heap supporting both `pop_max` and `pop_min` in O(log n).
+///
+/// Equal items are ordered by insertion, the older item being the greater one
+/// like in `StableBinaryHeap`. So `pop_max` returns equal items in inserted
+/// order while `pop_min` returns them in reverse, and popping from either end
+/// sees the same sequence of items
+#[derive(Clone)]
+pub struct StableMinMaxHeap<T, C = MaxComparator, N = usize> {
+    /// Min-max heap: items on even levels are smaller than all of their
+    /// descendants, items on odd levels greater
+    data: Vec<HeapItem<T, N>>,
+    counter: N,
+    cmp: C,
+}
+
+impl<T: Ord> StableMinMaxHeap<T> {
+    /// Creates a new stable min-max heap
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_comparator(MaxComparator)
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> StableMinMaxHeap<T, C, N> {
+    /// Creates a new stable min-max heap ordered by `cmp`
+    #[inline]
+    pub fn from_comparator(cmp: C) -> Self {
+        Self {
+            data: Vec::new(),
+            counter: N::ZERO,
+            cmp,
+        }
+    }
+
+    /// Pushes a new element on the heap
+    pub fn push(&mut self, item: T) {
+        if self.counter == N::MAX {
+            self.counter = counter::renormalize(&mut self.data);
+        }
+
+        let item = HeapItem::new(item, self.counter);
+        self.counter = counter::counter_add(self.counter, N::ONE);
+
+        self.data.push(item);
+        self.bubble_up(self.data.len() - 1);
+    }
+
+    /// Removes the greatest item and returns it. Of equal items the oldest one
+    /// is returned
+    pub fn pop_max(&mut self) -> Option<T> {
+        let pos = self.max_pos()?;
+        Some(self.remove_at(pos))
+    }
+
+    /// Removes the smallest item and returns it. Of equal items the newest one
+    /// is returned
+    pub fn pop_min(&mut self) -> Option<T> {
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(self.remove_at(0))
+    }
+
+    /// Returns the greatest item
+    #[inline]
+    pub fn peek_max(&self) -> Option<&T> {
+        self.max_pos().map(|pos| self.data[pos].inner())
+    }
+
+    /// Returns the smallest item
+    #[inline]
+    pub fn peek_min(&self) -> Option<&T> {
+        self.data.first().map(|i| i.inner())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.counter = N::ZERO;
+    }
+
+    /// Returns an iterator over all items in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.data.iter().map(|i| i.inner())
+    }
+
+    /// Returns all items in arbitrary order
+    #[inline]
+    pub fn into_vec(self) -> Vec<T> {
+        self.data.into_iter().map(|i| i.into_inner()).collect()
+    }
+
+    /// Returns the position of the greatest item, which is the root's greater child
+    fn max_pos(&self) -> Option<usize> {
+        match self.data.len() {
+            0 => None,
+            1 => Some(0),
+            2 => Some(1),
+            _ if self.cmp_at(2, 1) == Ordering::Greater => Some(2),
+            _ => Some(1),
+        }
+    }
+
+    /// Removes the item at `pos`, which has to be the root or one of its
+    /// children, and restores the heap property
+    fn remove_at(&mut self, pos: usize) -> T {
+        let item = self.data.swap_remove(pos);
+        if pos < self.data.len() {
+            self.trickle_down(pos);
+        }
+        item.into_inner()
+    }
+
+    /// Compares the items at the positions `a` and `b`
+    #[inline]
+    fn cmp_at(&self, a: usize, b: usize) -> Ordering {
+        self.cmp.compare_items(&self.data[a], &self.data[b])
+    }
+
+    /// Returns `true` if `pos` is on a level of smaller items
+    #[inline]
+    fn is_min_level(pos: usize) -> bool {
+        (pos + 1).ilog2() & 1 == 0
+    }
+
+    /// Moves a newly pushed item at `pos` up to its position
+    fn bubble_up(&mut self, pos: usize) {
+        if pos == 0 {
+            return;
+        }
+
+        let parent = (pos - 1) / 2;
+        if Self::is_min_level(pos) {
+            if self.cmp_at(pos, parent) == Ordering::Greater {
+                self.data.swap(pos, parent);
+                self.bubble_up_by(parent, Ordering::Greater);
+            } else {
+                self.bubble_up_by(pos, Ordering::Less);
+            }
+        } else if self.cmp_at(pos, parent) == Ordering::Less {
+            self.data.swap(pos, parent);
+            self.bubble_up_by(parent, Ordering::Less);
+        } else {
+            self.bubble_up_by(pos, Ordering::Greater);
+        }
+    }
+
+    /// Swaps the item at `pos` with its grandparents while it compares as `ord`
+    /// to them
+    fn bubble_up_by(&mut self, mut pos: usize, ord: Ordering) {
+        while pos > 2 {
+            let grandparent = (pos - 3) / 4;
+            if self.cmp_at(pos, grandparent) != ord {
+                break;
+            }
+
+            self.data.swap(pos, grandparent);
+            pos = grandparent;
+        }
+    }
+
+    /// Moves the item at `pos` down to its position
+    #[inline]
+    fn trickle_down(&mut self, pos: usize) {
+        if Self::is_min_level(pos) {
+            self.trickle_down_by(pos, Ordering::Less);
+        } else {
+            self.trickle_down_by(pos, Ordering::Greater);
+        }
+    }
+
+    /// Moves the item at `pos` down the levels of items comparing as `ord` to
+    /// their descendants
+    fn trickle_down_by(&mut self, mut pos: usize, ord: Ordering) {
+        let len = self.data.len();
+        loop {
+            let first_child = 2 * pos + 1;
+            if first_child >= len {
+                break;
+            }
+
+            // The most extreme of the children and grandchildren
+            let first_grandchild = 2 * first_child + 1;
+            let mut m = first_child;
+            for i in [first_child + 1]
+                .into_iter()
+                .chain(first_grandchild..first_grandchild + 4)
+                .filter(|i| *i < len)
+            {
+                if self.cmp_at(i, m) == ord {
+                    m = i;
+                }
+            }
+
+            if self.cmp_at(m, pos) != ord {
+                break;
+            }
+
+            self.data.swap(m, pos);
+            if m < first_grandchild {
+                break;
+            }
+
+            let parent = (m - 1) / 2;
+            if self.cmp_at(m, parent) == ord.reverse() {
+                self.data.swap(m, parent);
+            }
+            pos = m;
+        }
+    }
+}
+
+impl<T: Debug, C, N: Debug> Debug for StableMinMaxHeap<T, C, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableMinMaxHeap")
+            .field("data", &self.data)
+            .field("counter", &self.counter)
+            .finish()
+    }
+}
+
+impl<T, C: Compare<T> + Default, N: Counter> Default for StableMinMaxHeap<T, C, N> {
+    #[inline]
+    fn default() -> Self {
+        Self::from_comparator(C::default())
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> Extend<T> for StableMinMaxHeap<T, C, N> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for i in iter {
+            self.push(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableMinMaxHeap;
+    use crate::{
+        compare::KeyComparator,
+        test_util::{keyed_items, sorted_desc},
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_min_max_heap() {
+        let mut heap = StableMinMaxHeap::new();
+        assert_eq!(heap.pop_max(), None);
+        assert_eq!(heap.pop_min(), None);
+
+        heap.extend([(3, 'a'), (1, 'b'), (4, 'c'), (1, 'd'), (5, 'e')]);
+        assert_eq!(heap.peek_max(), Some(&(5, 'e')));
+        assert_eq!(heap.peek_min(), Some(&(1, 'b')));
+        assert_eq!(heap.len(), 5);
+    }
+
+    #[test]
+    fn test_min_max_heap_stability() {
+        let items = keyed_items(500, 17);
+        let expected = sorted_desc(items.iter().copied());
+
+        let mut heap: StableMinMaxHeap<_, _> =
+            StableMinMaxHeap::from_comparator(KeyComparator(|i: &(u32, u32)| i.0));
+        heap.extend(items.iter().copied());
+
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        for i in 0..items.len() {
+            if i % 3 == 0 {
+                back.push(heap.pop_min().unwrap());
+            } else {
+                front.push(heap.pop_max().unwrap());
+            }
+        }
+        assert!(heap.is_empty());
+
+        back.reverse();
+        front.extend(back);
+        assert_eq!(front, expected);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{
+    compare::{Compare, FnComparator, MaxComparator},
+    item::HeapItem,
+    StableBinaryHeap,
+};
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+};
+
+/// Immutable stable heap. `push` and `pop` return new versions of the heap
+/// sharing most of their nodes with the previous one, so keeping old versions
+/// around, e.g. as snapshots, is O(1). Backed by a persistent leftist heap,
+/// pushing and popping take O(log n)
+pub struct StableHeap<T, C = MaxComparator> {
+    root: Link<T>,
+    len: usize,
+    counter: usize,
+    cmp: C,
+}
+
+type Link<T> = Option<Arc<Node<T>>>;
+
+struct Node<T> {
+    item: Arc<HeapItem<T>>,
+    /// Length of the path to the nearest missing child, the right path is
+    /// always the shortest one
+    rank: usize,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+impl<T> Drop for Node<T> {
+    /// Drops the subtrees iteratively since the left paths can be long
+    fn drop(&mut self) {
+        let mut stack: Vec<_> = self
+            .left
+            .take()
+            .into_iter()
+            .chain(self.right.take())
+            .collect();
+        while let Some(node) = stack.pop() {
+            if let Ok(mut node) = Arc::try_unwrap(node) {
+                stack.extend(node.left.take());
+                stack.extend(node.right.take());
+            }
+        }
+    }
+}
+
+#[inline]
+fn rank<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.rank)
+}
+
+impl<T: Ord> StableHeap<T> {
+    /// Creates a new empty heap
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_comparator(MaxComparator)
+    }
+}
+
+impl<T, C: Compare<T>> StableHeap<T, C> {
+    /// Creates a new empty heap ordered by `cmp`
+    #[inline]
+    pub fn from_comparator(cmp: C) -> Self {
+        Self {
+            root: None,
+            len: 0,
+            counter: 0,
+            cmp,
+        }
+    }
+
+    /// Returns a new version of the heap containing `item`
+    pub fn push(&self, item: T) -> Self
+    where
+        C: Clone,
+    {
+        let node = Node {
+            item: Arc::new(HeapItem::new(item, self.counter)),
+            rank: 1,
+            left: None,
+            right: None,
+        };
+        Self {
+            root: self.merge(self.root.clone(), Some(Arc::new(node))),
+            len: self.len + 1,
+            counter: self.counter + 1,
+            cmp: self.cmp.clone(),
+        }
+    }
+
+    /// Returns the greatest item together with a new version of the heap
+    /// without it
+    pub fn pop(&self) -> Option<(&T, Self)>
+    where
+        C: Clone,
+    {
+        let root = self.root.as_ref()?;
+        let rest = Self {
+            root: self.merge(root.left.clone(), root.right.clone()),
+            len: self.len - 1,
+            counter: self.counter,
+            cmp: self.cmp.clone(),
+        };
+        Some((root.item.inner(), rest))
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.root.as_ref().map(|node| node.item.inner())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator visiting all items in heap order
+    pub fn iter_sorted(&self) -> impl Iterator<Item = &T> {
+        let mut frontier: StableBinaryHeap<&Node<T>, _> =
+            StableBinaryHeap::from_comparator(FnComparator(|a: &&Node<T>, b: &&Node<T>| {
+                self.cmp.compare_items(&a.item, &b.item)
+            }));
+        frontier.extend(self.root.as_deref());
+
+        core::iter::from_fn(move || {
+            let node = frontier.pop()?;
+            frontier.extend(node.left.as_deref());
+            frontier.extend(node.right.as_deref());
+            Some(node.item.inner())
+        })
+    }
+
+    fn merge(&self, a: Link<T>, b: Link<T>) -> Link<T> {
+        let (a, b) = match (a, b) {
+            (None, link) | (link, None) => return link,
+            (Some(a), Some(b)) => (a, b),
+        };
+        let (top, other) = match self.cmp.compare_items(&a.item, &b.item) {
+            Ordering::Less => (b, a),
+            _ => (a, b),
+        };
+
+        let merged = self.merge(top.right.clone(), Some(other));
+        let (left, right) = if rank(&top.left) >= rank(&merged) {
+            (top.left.clone(), merged)
+        } else {
+            (merged, top.left.clone())
+        };
+        Some(Arc::new(Node {
+            item: top.item.clone(),
+            rank: rank(&right) + 1,
+            left,
+            right,
+        }))
+    }
+}
+
+impl<T, C: Clone> Clone for StableHeap<T, C> {
+    /// Returns another handle to the same version in O(1)
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+            counter: self.counter,
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+
+impl<T: Debug, C: Compare<T>> Debug for StableHeap<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter_sorted()).finish()
+    }
+}
+
+impl<T: Ord> Default for StableHeap<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableHeap;
+    use crate::{
+        compare::KeyComparator,
+        test_util::{keyed_items, sorted_desc},
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_persistent_heap() {
+        let items = keyed_items(200, 9);
+        let cmp = KeyComparator(|i: &(u32, u32)| i.0);
+
+        let mut versions = Vec::from([StableHeap::from_comparator(cmp)]);
+        for item in &items {
+            let next = versions.last().unwrap().push(*item);
+            versions.push(next);
+        }
+
+        for (len, version) in versions.iter().enumerate() {
+            let expected = sorted_desc(items[..len].iter().copied());
+            assert_eq!(version.len(), len);
+            assert_eq!(version.iter_sorted().copied().collect::<Vec<_>>(), expected);
+        }
+
+        let full = versions.pop().unwrap();
+        let (top, rest) = full.pop().unwrap();
+        assert_eq!(top.0, 8);
+        assert_eq!(rest.len(), 199);
+        assert_eq!(full.len(), 200);
+        assert_eq!(rest.peek(), full.iter_sorted().nth(1));
+    }
+
+    #[test]
+    fn test_persistent_heap_drop() {
+        // Pushing ascending items builds a long left path
+        let mut heap = StableHeap::new();
+        for i in 0..100_000 {
+            heap = heap.push(i);
+        }
+        let mut version = heap.clone();
+        while let Some((_, rest)) = version.pop() {
+            version = rest;
+            if version.len() == 99_000 {
+                break;
+            }
+        }
+        drop(heap);
+        assert_eq!(version.peek(), Some(&98_999));
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{
+    compare::{Compare, MaxComparator, MinComparator},
+    counter::{counter_add, renormalize_counters, Counter},
+    item::HeapItem,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt::{self, Debug},
+    mem,
+};
+
+/// Priority queue mapping each key to a priority. Pops the key with the
+/// greatest priority first, keys with equal priorities are returned in the
+/// order they were pushed. Changing the priority of a key keeps its position
+/// among equal priorities
+#[derive(Clone)]
+pub struct StablePriorityQueue<K, P, C = MaxComparator, N = usize> {
+    data: Vec<Entry<K, P, N>>,
+    /// Position of each key in `data`
+    index: BTreeMap<K, usize>,
+    counter: N,
+    cmp: C,
+}
+
+#[derive(Clone)]
+struct Entry<K, P, N> {
+    key: K,
+    priority: HeapItem<P, N>,
+}
+
+impl<K: Ord + Clone, P: Ord> StablePriorityQueue<K, P> {
+    /// Creates a new queue popping the greatest priority first
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_comparator(MaxComparator)
+    }
+}
+
+impl<K: Ord + Clone, P: Ord> StablePriorityQueue<K, P, MinComparator> {
+    /// Creates a new queue popping the smallest priority first
+    #[inline]
+    pub fn new_min() -> Self {
+        Self::from_comparator(MinComparator)
+    }
+}
+
+impl<K: Ord + Clone, P, C: Compare<P>, N: Counter> StablePriorityQueue<K, P, C, N> {
+    /// Creates a new queue ordering the priorities by `cmp`
+    #[inline]
+    pub fn from_comparator(cmp: C) -> Self {
+        Self {
+            data: Vec::new(),
+            index: BTreeMap::new(),
+            counter: N::ZERO,
+            cmp,
+        }
+    }
+
+    /// Pushes `key` with `priority`. If `key` is already queued, only its
+    /// priority gets changed and the old priority is returned
+    pub fn push(&mut self, key: K, priority: P) -> Option<P> {
+        if self.index.contains_key(&key) {
+            return self.change_priority(&key, priority);
+        }
+
+        let priority = self.new_priority(priority);
+        let pos = self.data.len();
+        self.index.insert(key.clone(), pos);
+        self.data.push(Entry { key, priority });
+        self.sift_up(pos);
+        None
+    }
+
+    /// Changes the priority of `key` and returns the old one, or `None` if `key`
+    /// isn't queued. The key keeps its insertion counter, so it stays ordered
+    /// before all keys with an equal priority pushed after it
+    pub fn change_priority<Q>(&mut self, key: &Q, priority: P) -> Option<P>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = *self.index.get(key)?;
+        let old = mem::replace(self.data[pos].priority.inner_mut(), priority);
+        if self.sift_up(pos) == pos {
+            self.sift_down(pos);
+        }
+        Some(old)
+    }
+
+    /// Removes and returns the key with the greatest priority
+    pub fn pop(&mut self) -> Option<(K, P)> {
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(self.remove_at(0))
+    }
+
+    /// Removes `key` from the queue and returns it together with its priority
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<(K, P)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = *self.index.get(key)?;
+        Some(self.remove_at(pos))
+    }
+
+    /// Returns the key with the greatest priority
+    #[inline]
+    pub fn peek(&self) -> Option<(&K, &P)> {
+        self.data.first().map(|e| (&e.key, e.priority.inner()))
+    }
+
+    /// Returns the priority of `key`
+    #[inline]
+    pub fn get_priority<Q>(&self, key: &Q) -> Option<&P>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = *self.index.get(key)?;
+        Some(self.data[pos].priority.inner())
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.index.contains_key(key)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.index.clear();
+        self.counter = N::ZERO;
+    }
+
+    /// Returns an iterator over all keys and priorities in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &P)> {
+        self.data.iter().map(|e| (&e.key, e.priority.inner()))
+    }
+
+    /// Returns all keys and priorities sorted by priority, greatest first
+    pub fn into_sorted_vec(mut self) -> Vec<(K, P)> {
+        let mut out = Vec::with_capacity(self.len());
+        while let Some(entry) = self.pop() {
+            out.push(entry);
+        }
+        out
+    }
+
+    /// Wraps `priority` into a new `HeapItem` and increases the counter,
+    /// renormalizing the counters if it would overflow
+    fn new_priority(&mut self, priority: P) -> HeapItem<P, N> {
+        if self.counter == N::MAX {
+            self.renormalize();
+        }
+
+        let id = self.counter;
+        self.counter = counter_add(self.counter, N::ONE);
+        HeapItem::new(priority, id)
+    }
+
+    /// Renumbers the counters of all keys to `0..len` keeping their relative
+    /// insertion order
+    fn renormalize(&mut self) {
+        let mut counters: Vec<N> = self.data.iter().map(|e| e.priority.counter).collect();
+        self.counter = renormalize_counters(&mut counters);
+        for (entry, counter) in self.data.iter_mut().zip(counters) {
+            entry.priority.counter = counter;
+        }
+    }
+
+    /// Removes the entry at `pos` and restores the heap property
+    fn remove_at(&mut self, pos: usize) -> (K, P) {
+        let entry = self.data.swap_remove(pos);
+        self.index.remove(&entry.key);
+
+        if pos < self.len() {
+            if let Some(p) = self.index.get_mut(&self.data[pos].key) {
+                *p = pos;
+            }
+            if self.sift_up(pos) == pos {
+                self.sift_down(pos);
+            }
+        }
+
+        (entry.key, entry.priority.into_inner())
+    }
+
+    /// Compares the priorities at the positions `a` and `b`
+    #[inline]
+    fn cmp_at(&self, a: usize, b: usize) -> Ordering {
+        self.cmp
+            .compare_items(&self.data[a].priority, &self.data[b].priority)
+    }
+
+    /// Swaps the entries at `a` and `b` and updates their positions in the index
+    fn swap(&mut self, a: usize, b: usize) {
+        self.data.swap(a, b);
+        for pos in [a, b] {
+            if let Some(p) = self.index.get_mut(&self.data[pos].key) {
+                *p = pos;
+            }
+        }
+    }
+
+    /// Moves the entry at `pos` up until its parent is greater. Returns the new position
+    fn sift_up(&mut self, mut pos: usize) -> usize {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if self.cmp_at(pos, parent) != Ordering::Greater {
+                break;
+            }
+
+            self.swap(pos, parent);
+            pos = parent;
+        }
+
+        pos
+    }
+
+    /// Moves the entry at `pos` down until both of its children are smaller
+    fn sift_down(&mut self, mut pos: usize) {
+        let end = self.len();
+        loop {
+            let mut child = 2 * pos + 1;
+            if child >= end {
+                break;
+            }
+
+            if child + 1 < end && self.cmp_at(child + 1, child) == Ordering::Greater {
+                child += 1;
+            }
+
+            if self.cmp_at(child, pos) != Ordering::Greater {
+                break;
+            }
+
+            self.swap(pos, child);
+            pos = child;
+        }
+    }
+}
+
+impl<K: Debug, P: Debug, C, N: Debug> Debug for StablePriorityQueue<K, P, C, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.data.iter().map(|e| (&e.key, &e.priority)))
+            .finish()
+    }
+}
+
+impl<K, P, C, N> Default for StablePriorityQueue<K, P, C, N>
+where
+    K: Ord + Clone,
+    C: Compare<P> + Default,
+    N: Counter,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::from_comparator(C::default())
+    }
+}
+
+impl<K, P, C, N> Extend<(K, P)> for StablePriorityQueue<K, P, C, N>
+where
+    K: Ord + Clone,
+    C: Compare<P>,
+    N: Counter,
+{
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, P)>>(&mut self, iter: I) {
+        for (key, priority) in iter {
+            self.push(key, priority);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StablePriorityQueue;
+    use alloc::{vec, vec::Vec};
+
+    #[test]
+    fn test_priority_queue() {
+        let mut queue = StablePriorityQueue::new();
+        queue.extend([("a", 1), ("b", 3), ("c", 1), ("d", 2), ("e", 3)]);
+        assert_eq!(queue.len(), 5);
+        assert_eq!(queue.peek(), Some((&"b", &3)));
+
+        assert_eq!(queue.push("d", 3), Some(2));
+        assert_eq!(queue.change_priority("b", 1), Some(3));
+        assert_eq!(queue.change_priority("x", 1), None);
+        assert_eq!(queue.get_priority("b"), Some(&1));
+
+        assert_eq!(queue.remove("e"), Some(("e", 3)));
+        assert!(!queue.contains_key("e"));
+
+        let keys: Vec<_> = queue.into_sorted_vec().into_iter().map(|e| e.0).collect();
+        assert_eq!(keys, vec!["d", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_priority_queue_index() {
+        let mut queue = StablePriorityQueue::new_min();
+        for i in 0..200u32 {
+            queue.push(i, i % 7);
+        }
+        for i in (0..200).step_by(3) {
+            queue.remove(&i);
+        }
+        for i in (1..200).step_by(5) {
+            queue.change_priority(&i, 0);
+        }
+
+        let mut expected: Vec<_> = (0..200)
+            .filter(|i| i % 3 != 0)
+            .map(|i| (i, if i % 5 == 1 { 0 } else { i % 7 }))
+            .collect();
+        expected.sort_by_key(|e| e.1);
+
+        for (key, priority) in expected {
+            assert_eq!(queue.get_priority(&key), Some(&priority));
+            assert_eq!(queue.pop(), Some((key, priority)));
+        }
+        assert!(queue.is_empty());
+    }
+}
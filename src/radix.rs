@@ -0,0 +1,207 @@
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    mem,
+};
+
+/// Unsigned integer key of a `StableRadixHeap`
+pub trait RadixKey: Copy + Ord {
+    const ZERO: Self;
+    const BITS: usize;
+
+    /// Returns the index of the highest bit in which `self` and `last` differ,
+    /// plus one. Zero if they are equal
+    fn bucket(self, last: Self) -> usize;
+}
+
+macro_rules! impl_radix_key {
+    ($($t:ty),*) => {
+        $(
+            impl RadixKey for $t {
+                const ZERO: Self = 0;
+                const BITS: usize = <$t>::BITS as usize;
+
+                #[inline]
+                fn bucket(self, last: Self) -> usize {
+                    (<$t>::BITS - (self ^ last).leading_zeros()) as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_radix_key!(u8, u16, u32, u64, u128, usize);
+
+/// Monotone min-heap for integer keys, e.g. distances in Dijkstra's algorithm.
+/// Items with equal keys are returned in insertion order. Pushed keys must not
+/// be smaller than the last popped key, which allows bucketing the items by
+/// the highest bit differing from it. Pushing is O(1) and popping O(log K)
+/// amortized, where K is the key range
+#[derive(Clone)]
+pub struct StableRadixHeap<T, K = u64> {
+    /// Bucket `i` holds the items whose key differs from `last` first in bit
+    /// `i - 1`, so bucket 0 holds the items with key `last`. Every bucket is
+    /// in insertion order
+    buckets: Vec<VecDeque<(K, T)>>,
+    last: K,
+    len: usize,
+}
+
+impl<T, K: RadixKey> StableRadixHeap<T, K> {
+    /// Creates a new empty heap
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..=K::BITS).map(|_| VecDeque::new()).collect(),
+            last: K::ZERO,
+            len: 0,
+        }
+    }
+
+    /// Pushes `item` with `key`. Panics if `key` is smaller than the last
+    /// popped key
+    pub fn push(&mut self, key: K, item: T) {
+        assert!(
+            key >= self.last,
+            "key is smaller than the last popped key of the radix heap"
+        );
+        self.buckets[key.bucket(self.last)].push_back((key, item));
+        self.len += 1;
+    }
+
+    /// Removes the item with the smallest key and returns it together with
+    /// its key
+    pub fn pop(&mut self) -> Option<(K, T)> {
+        if self.buckets[0].is_empty() {
+            self.redistribute()?;
+        }
+        self.len -= 1;
+        self.buckets[0].pop_front()
+    }
+
+    /// Returns the item with the smallest key together with its key
+    pub fn peek(&self) -> Option<(K, &T)> {
+        let bucket = self.buckets.iter().find(|b| !b.is_empty())?;
+        bucket
+            .iter()
+            .min_by_key(|(key, _)| *key)
+            .map(|(key, item)| (*key, item))
+    }
+
+    /// Returns the key of the last popped item, which is the lower bound for
+    /// new keys
+    #[inline]
+    pub fn last_key(&self) -> K {
+        self.last
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all items and resets the last popped key to zero
+    pub fn clear(&mut self) {
+        for bucket in &mut self.buckets {
+            bucket.clear();
+        }
+        self.last = K::ZERO;
+        self.len = 0;
+    }
+
+    /// Returns an iterator over all keys and items in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (K, &T)> {
+        self.buckets
+            .iter()
+            .flatten()
+            .map(|(key, item)| (*key, item))
+    }
+
+    /// Moves the items of the first non empty bucket to the lower buckets
+    /// relative to their smallest key. Returns `None` if the heap is empty
+    fn redistribute(&mut self) -> Option<()> {
+        let pos = self.buckets.iter().position(|b| !b.is_empty())?;
+        let mut bucket = mem::take(&mut self.buckets[pos]);
+        self.last = bucket.iter().map(|(key, _)| *key).min()?;
+
+        // All items land in lower buckets, which are empty, so the insertion
+        // order of equal keys is kept
+        for (key, item) in bucket.drain(..) {
+            self.buckets[key.bucket(self.last)].push_back((key, item));
+        }
+        self.buckets[pos] = bucket;
+        Some(())
+    }
+}
+
+impl<T: Debug, K: RadixKey + Debug> Debug for StableRadixHeap<T, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, K: RadixKey> Default for StableRadixHeap<T, K> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, K: RadixKey> Extend<(K, T)> for StableRadixHeap<T, K> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        for (key, item) in iter {
+            self.push(key, item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableRadixHeap;
+    use crate::test_util::scatter;
+    use crate::StableBinaryHeap;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_radix_heap() {
+        let items: Vec<_> = (0..1000u32)
+            .map(|i| (u64::from(scatter(i, 50)), i))
+            .collect();
+        let mut heap = StableRadixHeap::new();
+        heap.extend(items.iter().copied());
+        assert_eq!(heap.len(), 1000);
+
+        let mut expected = items;
+        expected.sort_by_key(|i| i.0);
+        assert_eq!(heap.peek(), Some((0, &expected[0].1)));
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(out, expected);
+        assert_eq!(heap.last_key(), 49);
+    }
+
+    #[test]
+    fn test_radix_heap_monotone() {
+        let mut heap = StableRadixHeap::<u32, u32>::new();
+        let mut reference: StableBinaryHeap<_, _> =
+            StableBinaryHeap::new_by_key(|i: &(u32, u32)| core::cmp::Reverse(i.0));
+
+        for i in 0..2000u32 {
+            let key = heap.last_key() + scatter(i, 20);
+            heap.push(key, i);
+            reference.push((key, i));
+            if i % 3 == 0 {
+                assert_eq!(heap.pop(), reference.pop());
+            }
+        }
+        assert_eq!(heap.len(), reference.len());
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(out, reference.into_sorted_vec());
+    }
+}
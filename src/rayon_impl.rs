@@ -0,0 +1,61 @@
+use crate::{buffer::Allocator, compare::Compare, counter::Counter, StableBinaryHeap};
+use alloc::vec::Vec;
+use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
+
+/// Collects the items in parallel and pushes them in their original order, so
+/// the heap is identical to the one built by `Extend`
+impl<T, C, N, A> ParallelExtend<T> for StableBinaryHeap<T, C, N, A>
+where
+    T: Send,
+    C: Compare<T>,
+    N: Counter,
+    A: Allocator,
+{
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, par_iter: I) {
+        let items: Vec<T> = par_iter.into_par_iter().collect();
+        self.extend(items);
+    }
+}
+
+/// Numbers the items by their original index, so the heap is identical to the
+/// one built by `FromIterator`
+impl<T, C, N> FromParallelIterator<T> for StableBinaryHeap<T, C, N>
+where
+    T: Send,
+    C: Compare<T> + Default,
+    N: Counter,
+{
+    fn from_par_iter<I: IntoParallelIterator<Item = T>>(par_iter: I) -> Self {
+        let items: Vec<T> = par_iter.into_par_iter().collect();
+        Self::from(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StableBinaryHeap;
+    use alloc::vec::Vec;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_from_par_iter() {
+        let items: Vec<_> = (0..10_000u32).map(|i| (i % 13, i)).collect();
+        let key = |i: &(u32, u32)| i.0;
+
+        let par: StableBinaryHeap<_> = items.par_iter().copied().collect();
+        let seq: StableBinaryHeap<_> = items.iter().copied().collect();
+        assert_eq!(par.counter(), seq.counter());
+        assert_eq!(par.into_sorted_vec(), seq.into_sorted_vec());
+
+        let mut par = StableBinaryHeap::new_by_key(key);
+        let mut seq = StableBinaryHeap::new_by_key(key);
+        par.push((5, 0));
+        seq.push((5, 0));
+        par.par_extend(items.par_iter().copied());
+        seq.extend(items.iter().copied());
+
+        let par: Vec<_> = par.into_iter_sorted().collect();
+        let seq: Vec<_> = seq.into_iter_sorted().collect();
+        assert_eq!(par, seq);
+    }
+}
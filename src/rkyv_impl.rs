@@ -0,0 +1,230 @@
+use crate::{
+    buffer::{Allocator, Buffer},
+    compare::Compare,
+    counter::Counter,
+    stats::Stats,
+    ArchivedStableBinaryHeap, StableBinaryHeap,
+};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+use rkyv::{
+    api::high::HighValidator,
+    bytecheck::CheckBytes,
+    rancor::{Error, Fallible, Source},
+    ser::{Allocator as SerAllocator, Writer},
+    vec::{ArchivedVec, VecResolver},
+    Archive, Archived, Deserialize, DeserializeUnsized, Place, Serialize,
+};
+
+/// Buffers are archived like a `Vec`, independent of their allocator
+impl<T: Archive, A: Allocator> Archive for Buffer<T, A> {
+    type Archived = ArchivedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    #[inline]
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+impl<T, A, S> Serialize<S> for Buffer<T, A>
+where
+    T: Serialize<S>,
+    A: Allocator,
+    S: Fallible + SerAllocator + Writer + ?Sized,
+{
+    #[inline]
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        ArchivedVec::<T::Archived>::serialize_from_slice(&self[..], serializer)
+    }
+}
+
+impl<T, A, D> Deserialize<Buffer<T, A>, D> for ArchivedVec<T::Archived>
+where
+    T: Archive,
+    A: Allocator + Default,
+    [T::Archived]: DeserializeUnsized<[T], D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    #[inline]
+    fn deserialize(&self, deserializer: &mut D) -> Result<Buffer<T, A>, D::Error> {
+        let vec: Vec<T> = Deserialize::<Vec<T>, D>::deserialize(self, deserializer)?;
+        Ok(Buffer::from_vec_in(vec, A::default()))
+    }
+}
+
+/// Archived heap whose items don't match its counters
+#[derive(Debug)]
+struct InvalidHeap(String);
+
+impl fmt::Display for InvalidHeap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid archived heap: {}", self.0)
+    }
+}
+
+impl core::error::Error for InvalidHeap {}
+
+impl<T, C, N, A> StableBinaryHeap<T, C, N, A>
+where
+    T: Archive,
+    N: Archive,
+    Archived<N>: Ord,
+    A: Allocator,
+    ArchivedStableBinaryHeap<T, C, N, A>: for<'a> CheckBytes<HighValidator<'a, Error>>,
+{
+    /// Validates `bytes` and returns a zero-copy view of the archived heap.
+    /// Besides the bytes, every item needs a counter and the counters have to
+    /// be unique and below the heap's counter. The archived items are stored
+    /// in heap order, so the greatest item can be read without deserializing
+    pub fn access_archived(bytes: &[u8]) -> Result<&ArchivedStableBinaryHeap<T, C, N, A>, Error> {
+        let archived = rkyv::access::<ArchivedStableBinaryHeap<T, C, N, A>, Error>(bytes)?;
+        archived.check_counters().map_err(Error::new)?;
+        Ok(archived)
+    }
+}
+
+/// Validates the counters and rebuilds the heap, so items pop in the same
+/// order as in the archived heap. The comparator is not archived
+impl<T, C, N, A, D> Deserialize<StableBinaryHeap<T, C, N, A>, D>
+    for ArchivedStableBinaryHeap<T, C, N, A>
+where
+    T: Archive,
+    C: Compare<T> + Default,
+    N: Counter + Archive,
+    Archived<N>: Ord + Deserialize<N, D>,
+    A: Allocator + Default,
+    [T::Archived]: DeserializeUnsized<[T], D>,
+    [Archived<N>]: DeserializeUnsized<[N], D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<StableBinaryHeap<T, C, N, A>, D::Error> {
+        self.check_counters().map_err(D::Error::new)?;
+
+        let mut heap = StableBinaryHeap {
+            items: self.items.deserialize(deserializer)?,
+            counters: self.counters.deserialize(deserializer)?,
+            counter: self.counter.deserialize(deserializer)?,
+            epoch: 0,
+            cmp: C::default(),
+            stats: Stats::new(),
+        };
+        heap.rebuild();
+        Ok(heap)
+    }
+}
+
+impl<T: Archive, C, N: Archive, A: Allocator> ArchivedStableBinaryHeap<T, C, N, A> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the greatest archived item
+    #[inline]
+    pub fn peek(&self) -> Option<&Archived<T>> {
+        self.items.first()
+    }
+
+    /// Returns an iterator over all archived items in arbitrary order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Archived<T>> {
+        self.items.iter()
+    }
+
+    /// Returns the archived items and their counters. The counter of the
+    /// item at position `i` is the one at position `i`
+    #[inline]
+    pub fn as_slices(&self) -> (&[Archived<T>], &[Archived<N>]) {
+        (&self.items, &self.counters)
+    }
+
+    /// Get the archived heap's counter.
+    #[inline]
+    pub fn counter(&self) -> &Archived<N> {
+        &self.counter
+    }
+
+    /// Verifies that every item has a counter and that all counters are
+    /// unique and below the heap's counter
+    fn check_counters(&self) -> Result<(), InvalidHeap>
+    where
+        Archived<N>: Ord,
+    {
+        if self.items.len() != self.counters.len() {
+            return Err(InvalidHeap(
+                "amount of items and counters differs".to_string(),
+            ));
+        }
+        if self.counters.iter().any(|c| *c >= self.counter) {
+            return Err(InvalidHeap(
+                "item counter exceeds the heap's counter".to_string(),
+            ));
+        }
+
+        let mut counters: Vec<_> = self.counters.iter().collect();
+        counters.sort_unstable();
+        if counters.windows(2).any(|w| w[0] == w[1]) {
+            return Err(InvalidHeap("counter is used by multiple items".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StableBinaryHeap;
+    use rkyv::rancor::Error;
+
+    #[test]
+    fn test_rkyv_roundtrip() {
+        let mut heap = StableBinaryHeap::new();
+        for i in 0..100u32 {
+            heap.push(i % 7);
+        }
+
+        let bytes = rkyv::to_bytes::<Error>(&heap).unwrap();
+        let archived = StableBinaryHeap::<u32>::access_archived(&bytes).unwrap();
+        assert_eq!(archived.len(), 100);
+        assert_eq!(*archived.peek().unwrap(), 6);
+        assert_eq!(archived.counter().to_native(), 100);
+
+        let restored: StableBinaryHeap<u32> = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(restored.counter(), heap.counter());
+        assert_eq!(restored.into_sorted_vec(), heap.into_sorted_vec());
+    }
+
+    #[test]
+    fn test_rkyv_invalid_counters() {
+        let heap: StableBinaryHeap<u32> = (0..10).collect();
+
+        let mut missing = heap.clone();
+        missing.counters.truncate(5);
+        let mut duplicate = heap.clone();
+        duplicate.counters[1] = duplicate.counters[0];
+        let mut out_of_range = heap;
+        out_of_range.counters[3] = 10;
+
+        for heap in [missing, duplicate, out_of_range] {
+            let bytes = rkyv::to_bytes::<Error>(&heap).unwrap();
+            assert!(StableBinaryHeap::<u32>::access_archived(&bytes).is_err());
+            assert!(rkyv::from_bytes::<StableBinaryHeap<u32>, Error>(&bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn test_rkyv_invalid_bytes() {
+        let bytes = [0xffu8; 7];
+        assert!(StableBinaryHeap::<u32>::access_archived(&bytes).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{
+    buffer::{Allocator, Buffer},
+    compare::Compare,
+    counter::Counter,
+    item::HeapItem,
+    StableBinaryHeap,
+};
+use alloc::vec::Vec;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize)]
+#[serde(rename = "StableBinaryHeap", bound = "T: Serialize, N: Serialize")]
+struct RawHeapRef<'a, T, N> {
+    #[serde(serialize_with = "serialize_items")]
+    data: (&'a [T], &'a [N]),
+    counter: &'a N,
+}
+
+/// Serializes the items paired with their counters, the same way a slice of
+/// `HeapItem`s is serialized
+fn serialize_items<S, T, N>(data: &(&[T], &[N]), serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+    N: Serialize,
+{
+    let (items, counters) = data;
+    serializer.collect_seq(
+        items
+            .iter()
+            .zip(*counters)
+            .map(|(i, c)| HeapItem::new(i, c)),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "StableBinaryHeap")]
+struct RawHeap<T, N> {
+    data: Vec<HeapItem<T, N>>,
+    counter: N,
+}
+
+/// Serializes the items together with their counters and the heap's counter.
+/// The comparator is not serialized
+impl<T, C, N, A> Serialize for StableBinaryHeap<T, C, N, A>
+where
+    T: Serialize,
+    N: Serialize,
+    A: Allocator,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawHeapRef {
+            data: (&self.items, &self.counters),
+            counter: &self.counter,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Deserializes a heap serialized with the same counter type. The heap gets
+/// rebuilt, so items pop in the same order as in the serialized heap
+impl<'de, T, C, N, A> Deserialize<'de> for StableBinaryHeap<T, C, N, A>
+where
+    T: Deserialize<'de>,
+    C: Compare<T> + Default,
+    N: Counter + Deserialize<'de>,
+    A: Allocator + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let RawHeap { data, counter } = RawHeap::deserialize(deserializer)?;
+
+        let (items, counters) = data.into_iter().map(|i| (i.inner, i.counter)).unzip();
+        let mut heap = Self {
+            items: Buffer::from_vec_in(items, A::default()),
+            counters: Buffer::from_vec_in(counters, A::default()),
+            counter,
+            epoch: 0,
+            cmp: C::default(),
+            stats: Default::default(),
+        };
+        heap.check_counters().map_err(D::Error::custom)?;
+        heap.rebuild();
+        Ok(heap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StableBinaryHeap;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let mut heap = StableBinaryHeap::new();
+        for i in 0..100u32 {
+            heap.push((i % 7, i));
+        }
+        for _ in 0..10 {
+            heap.pop();
+        }
+
+        let json = serde_json::to_string(&heap).unwrap();
+        let mut restored: StableBinaryHeap<(u32, u32)> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.counter(), heap.counter());
+
+        heap.push((3, 100));
+        restored.push((3, 100));
+        assert_eq!(restored.into_sorted_vec(), heap.into_sorted_vec());
+    }
+
+    #[test]
+    fn test_serde_invalid_counter() {
+        let json = r#"{"data":[{"inner":1,"counter":4}],"counter":3}"#;
+        assert!(serde_json::from_str::<StableBinaryHeap<u32>>(json).is_err());
+    }
+
+    #[test]
+    fn test_serde_duplicate_counter() {
+        let json = r#"{"data":[{"inner":1,"counter":0},{"inner":2,"counter":0}],"counter":3}"#;
+        let err = serde_json::from_str::<StableBinaryHeap<u32>>(json).unwrap_err();
+        assert!(err.to_string().contains("used by multiple items"));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{buffer::Allocator, compare::Compare, counter::Counter, StableBinaryHeap};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::mem;
+
+/// Reports the bytes an item owns on the heap, not counting its own size
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! impl_heap_size_zero {
+    ($($t:ty),*) => {
+        $(
+            impl HeapSize for $t {
+                #[inline]
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_heap_size_zero!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    &str
+);
+
+impl HeapSize for String {
+    #[inline]
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Box<T> {
+    #[inline]
+    fn heap_size(&self) -> usize {
+        mem::size_of::<T>() + T::heap_size(self)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    #[inline]
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size)
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    #[inline]
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> StableBinaryHeap<T, C, N, A> {
+    /// Returns the bytes allocated for the items, including the space taken
+    /// by their counters and the unused capacity
+    #[inline]
+    pub fn memory_usage(&self) -> usize {
+        self.items.capacity() * mem::size_of::<T>() + self.counters.capacity() * mem::size_of::<N>()
+    }
+
+    /// Returns `memory_usage` plus the bytes the items own on the heap
+    pub fn deep_memory_usage(&self) -> usize
+    where
+        T: HeapSize,
+    {
+        self.memory_usage() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StableBinaryHeap;
+    use alloc::{string::String, vec, vec::Vec};
+    use core::mem;
+
+    #[test]
+    fn test_memory_usage() {
+        let mut heap: StableBinaryHeap<String> = StableBinaryHeap::with_capacity(10);
+        assert_eq!(
+            heap.memory_usage(),
+            10 * (mem::size_of::<String>() + mem::size_of::<usize>())
+        );
+
+        heap.push(String::with_capacity(100));
+        heap.push(String::with_capacity(20));
+        assert_eq!(heap.deep_memory_usage(), heap.memory_usage() + 120);
+
+        let mut heap: StableBinaryHeap<(u32, Vec<u64>)> = StableBinaryHeap::new();
+        heap.push((1, vec![1, 2, 3]));
+        assert_eq!(
+            heap.deep_memory_usage(),
+            heap.memory_usage() + 3 * mem::size_of::<u64>()
+        );
+
+        // Items and counters are stored apart, so small items aren't padded
+        let heap: StableBinaryHeap<u8> = StableBinaryHeap::with_capacity(10);
+        assert_eq!(heap.memory_usage(), 10 * 9);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{compare::FnComparator, StableBinaryHeap, StableBoundedHeap};
+use alloc::{vec, vec::Vec};
+use core::{cell::RefCell, cmp::Ordering};
+
+/// Sorts `v` in ascending order, keeping equal items in their original order.
+/// Runs in O(n log n) in the worst case. Only the positions of the items are
+/// sorted, using O(n) additional memory, the items are then swapped into place
+#[inline]
+pub fn stable_heapsort<T: Ord>(v: &mut [T]) {
+    stable_heapsort_by(v, T::cmp);
+}
+
+/// Sorts `v` in ascending order of the key extracted by `f`, keeping items
+/// with equal keys in their original order
+#[inline]
+pub fn stable_heapsort_by_key<T, K, F>(v: &mut [T], mut f: F)
+where
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    stable_heapsort_by(v, |a, b| f(a).cmp(&f(b)));
+}
+
+/// Sorts `v` in ascending order by `cmp`, keeping equal items in their
+/// original order
+pub fn stable_heapsort_by<T, F>(v: &mut [T], cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let order = sorted_positions(v, cmp);
+    apply_permutation(v, order);
+}
+
+/// Moves the `k` smallest items of `v` to its front in ascending order,
+/// keeping equal items in their original order. The order of the remaining
+/// items is unspecified. Runs in O(n log k)
+#[inline]
+pub fn partial_sort<T: Ord>(v: &mut [T], k: usize) {
+    partial_sort_by(v, k, T::cmp);
+}
+
+/// Moves the `k` smallest items of `v` by `cmp` to its front in ascending
+/// order, keeping equal items in their original order
+pub fn partial_sort_by<T, F>(v: &mut [T], k: usize, cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let cmp = RefCell::new(cmp);
+    // Reversing the order makes the smallest items the ones the bounded heap
+    // keeps. Of equal items it keeps the ones at lower positions
+    let mut smallest: StableBoundedHeap<_, _> = StableBoundedHeap::from_comparator(
+        k,
+        FnComparator(|a: &usize, b: &usize| (cmp.borrow_mut())(&v[*b], &v[*a])),
+    );
+    for pos in 0..v.len() {
+        smallest.push(pos);
+    }
+    let mut order = smallest.into_sorted_vec();
+
+    let mut front = vec![false; v.len()];
+    for pos in &order {
+        front[*pos] = true;
+    }
+    order.extend((0..v.len()).filter(|pos| !front[*pos]));
+    apply_permutation(v, order);
+}
+
+/// Reorders `v` so that the item at `n` is the one a stable sort would put
+/// there, with all items before it being smaller or equal and all items after
+/// it greater or equal. Returns the item at `n`. Panics if `n >= v.len()`
+#[inline]
+pub fn select_nth_stable<T: Ord>(v: &mut [T], n: usize) -> &mut T {
+    select_nth_stable_by(v, n, T::cmp)
+}
+
+/// Reorders `v` so that the item at `n` is the one a stable sort by `cmp`
+/// would put there. Returns the item at `n`. Panics if `n >= v.len()`
+pub fn select_nth_stable_by<T, F>(v: &mut [T], n: usize, cmp: F) -> &mut T
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    assert!(n < v.len(), "n out of bounds");
+    partial_sort_by(v, n + 1, cmp);
+    &mut v[n]
+}
+
+/// Heapsorts the positions of the items of `v`. The positions double as the
+/// heap's counters, so equal items stay in their original order
+fn sorted_positions<T, F>(v: &[T], cmp: F) -> Vec<usize>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let cmp = RefCell::new(cmp);
+    let positions = (0..v.len()).collect();
+    let heap: StableBinaryHeap<_, _> = StableBinaryHeap::from_vec_and_comparator(
+        positions,
+        FnComparator(|a: &usize, b: &usize| (cmp.borrow_mut())(&v[*a], &v[*b])),
+    );
+    heap.into_sorted_vec_asc()
+}
+
+/// Reorders `v` so that position `i` holds the item previously at `order[i]`
+fn apply_permutation<T>(v: &mut [T], mut order: Vec<usize>) {
+    for start in 0..v.len() {
+        let mut pos = start;
+        loop {
+            let src = order[pos];
+            // Marks `pos` as done
+            order[pos] = pos;
+            if src == start || src == pos {
+                break;
+            }
+            v.swap(pos, src);
+            pos = src;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        partial_sort, partial_sort_by, select_nth_stable, select_nth_stable_by, stable_heapsort,
+        stable_heapsort_by, stable_heapsort_by_key,
+    };
+    use crate::test_util::{keyed_items, sorted_desc};
+    use alloc::{string::String, vec::Vec};
+
+    #[test]
+    fn test_stable_heapsort() {
+        for len in [0, 1, 2, 7, 100, 1000] {
+            let items = keyed_items(len as u32, 13);
+
+            let mut expected = items.clone();
+            expected.sort_by_key(|i| i.0);
+            let mut sorted = items.clone();
+            stable_heapsort_by_key(&mut sorted, |i| i.0);
+            assert_eq!(sorted, expected);
+
+            let expected = sorted_desc(items.iter().copied());
+            let mut sorted = items.clone();
+            stable_heapsort_by(&mut sorted, |a, b| b.0.cmp(&a.0));
+            assert_eq!(sorted, expected);
+        }
+
+        let mut words: Vec<String> = ["pear", "apple", "fig", "apple"].map(String::from).into();
+        stable_heapsort(&mut words);
+        assert_eq!(words, ["apple", "apple", "fig", "pear"]);
+    }
+
+    #[test]
+    fn test_partial_sort() {
+        let items = keyed_items(300, 17);
+        let mut expected = items.clone();
+        expected.sort_by_key(|i| i.0);
+
+        for k in [0, 1, 10, 150, 300, 400] {
+            let mut v = items.clone();
+            partial_sort_by(&mut v, k, |a, b| a.0.cmp(&b.0));
+            let k = k.min(v.len());
+            assert_eq!(v[..k], expected[..k]);
+
+            let mut rest = v[k..].to_vec();
+            rest.sort_by_key(|i| i.1);
+            let mut expected_rest = expected[k..].to_vec();
+            expected_rest.sort_by_key(|i| i.1);
+            assert_eq!(rest, expected_rest);
+        }
+
+        let mut v = [5, 1, 4, 1, 3];
+        partial_sort(&mut v, 3);
+        assert_eq!(v[..3], [1, 1, 3]);
+    }
+
+    #[test]
+    fn test_select_nth_stable() {
+        let items = keyed_items(100, 7);
+        let mut expected = items.clone();
+        expected.sort_by_key(|i| i.0);
+
+        for n in [0, 13, 50, 99] {
+            let mut v = items.clone();
+            let nth = *select_nth_stable_by(&mut v, n, |a, b| a.0.cmp(&b.0));
+            assert_eq!(nth, expected[n]);
+            assert!(v[..n].iter().all(|i| i.0 <= nth.0));
+            assert!(v[n + 1..].iter().all(|i| i.0 >= nth.0));
+        }
+
+        assert_eq!(*select_nth_stable(&mut [3, 1, 2], 1), 2);
+    }
+}
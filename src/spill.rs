@@ -0,0 +1,331 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    item::HeapItem,
+    StableBinaryHeap, StablePeekMut,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    cmp::Ordering,
+    env,
+    fmt::{self, Debug},
+    format,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    mem,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{self, AtomicUsize},
+    vec,
+    vec::Vec,
+};
+
+/// Numbers the run files of all heaps in this process
+static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+/// Stable heap for more items than fit into memory. Once `budget` items are
+/// held in memory, they get written to a temporary file as a sorted run.
+/// Popping merges the items in memory with the greatest remaining item of
+/// every run, so equal items are returned in insertion order across runs too.
+///
+/// Items are stored in the files with `postcard`, each run file is deleted
+/// once it is fully consumed or the heap is dropped
+pub struct SpillableStableHeap<T, C = MaxComparator> {
+    memory: StableBinaryHeap<T, C, u64>,
+    runs: StableBinaryHeap<Run<T>, RunComparator<C>>,
+    budget: usize,
+    dir: PathBuf,
+    len: usize,
+}
+
+/// Sorted run spilled to a file together with its greatest remaining item
+struct Run<T> {
+    head: HeapItem<T, u64>,
+    file: RunFile,
+}
+
+struct RunFile {
+    path: PathBuf,
+    reader: Option<BufReader<File>>,
+    /// Amount of items not read yet
+    remaining: usize,
+}
+
+impl RunFile {
+    fn read_next<T: DeserializeOwned>(&mut self) -> io::Result<Option<HeapItem<T, u64>>> {
+        let Some(reader) = self.reader.as_mut().filter(|_| self.remaining > 0) else {
+            return Ok(None);
+        };
+
+        let mut len = [0; 8];
+        reader.read_exact(&mut len)?;
+        let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+        reader.read_exact(&mut bytes)?;
+        let (counter, item) = postcard::from_bytes(&bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        self.remaining -= 1;
+        Ok(Some(HeapItem::new(item, counter)))
+    }
+}
+
+impl RunFile {
+    /// Creates a new run file in `dir`. Existing files, or symlinks, with the
+    /// same name are never opened, the next run number is tried instead
+    fn create(dir: &Path) -> io::Result<(Self, File)> {
+        loop {
+            let run = NEXT_RUN.fetch_add(1, atomic::Ordering::Relaxed);
+            let path = dir.join(format!("stable-binary-heap-{}-{run}.run", process::id()));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => {
+                    let run = Self {
+                        path,
+                        reader: None,
+                        remaining: 0,
+                    };
+                    return Ok((run, file));
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl Drop for RunFile {
+    fn drop(&mut self) {
+        // Closes the file first, which some platforms require for removing it
+        self.reader = None;
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Orders runs by their greatest remaining item
+#[derive(Clone, Copy)]
+struct RunComparator<C>(C);
+
+impl<T, C: Compare<T>> Compare<Run<T>> for RunComparator<C> {
+    #[inline]
+    fn compare(&self, a: &Run<T>, b: &Run<T>) -> Ordering {
+        self.0.compare_items(&a.head, &b.head)
+    }
+}
+
+impl<T: Ord + Serialize + DeserializeOwned> SpillableStableHeap<T> {
+    /// Creates a new empty heap holding up to `budget` items in memory and
+    /// spilling to the system's temporary directory
+    #[inline]
+    pub fn new(budget: usize) -> Self {
+        Self::from_comparator(budget, MaxComparator)
+    }
+}
+
+impl<T, C> SpillableStableHeap<T, C>
+where
+    T: Serialize + DeserializeOwned,
+    C: Compare<T> + Clone,
+{
+    /// Creates a new empty heap ordered by `cmp` holding up to `budget` items
+    /// in memory and spilling to the system's temporary directory
+    pub fn from_comparator(budget: usize, cmp: C) -> Self {
+        Self {
+            memory: StableBinaryHeap::from_comparator(cmp.clone()),
+            runs: StableBinaryHeap::from_comparator(RunComparator(cmp)),
+            budget: budget.max(1),
+            dir: env::temp_dir(),
+            len: 0,
+        }
+    }
+
+    /// Spills to `dir` instead of the system's temporary directory
+    #[inline]
+    pub fn with_spill_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    /// Pushes `item`, spilling all items in memory to a new run file once the
+    /// budget is reached
+    pub fn push(&mut self, item: T) -> io::Result<()> {
+        self.memory.push(item);
+        self.len += 1;
+        if self.memory.len() >= self.budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Removes the greatest item and returns it, reading the next item of its
+    /// run if it was spilled
+    pub fn pop(&mut self) -> io::Result<Option<T>> {
+        if !self.top_is_spilled() {
+            let item = self.memory.pop();
+            self.len -= usize::from(item.is_some());
+            return Ok(item);
+        }
+
+        let mut run = self.runs.peek_mut().expect("spilled top without runs");
+        let item = match run.file.read_next()? {
+            Some(next) => mem::replace(&mut run.head, next),
+            None => StablePeekMut::pop(run).head,
+        };
+        self.len -= 1;
+        Ok(Some(item.into_inner()))
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        if self.top_is_spilled() {
+            self.runs.peek().map(|run| run.head.inner())
+        } else {
+            self.memory.peek()
+        }
+    }
+
+    /// Returns the amount of items, including the spilled ones
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the amount of run files that haven't been fully consumed
+    #[inline]
+    pub fn runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Returns `true` if the greatest item is the head of a run
+    fn top_is_spilled(&self) -> bool {
+        match (self.memory.peek_with_seq(), self.runs.peek()) {
+            (Some((top, counter)), Some(run)) => {
+                let head = &run.head;
+                self.memory
+                    .cmp
+                    .compare_parts(top, &counter, &head.inner, &head.counter)
+                    == Ordering::Less
+            }
+            (_, run) => run.is_some(),
+        }
+    }
+
+    /// Writes all items in memory to a new run file in heap order. The items
+    /// keep their counters, which stay unique since the counter of `memory` is
+    /// never reset
+    fn spill(&mut self) -> io::Result<()> {
+        let (items, counters) = self.memory.as_slices();
+        let mut items: Vec<_> = items.iter().zip(counters).collect();
+        let cmp = &self.memory.cmp;
+        items.sort_unstable_by(|a, b| cmp.compare_parts(b.0, b.1, a.0, a.1));
+
+        let (mut file, writer) = RunFile::create(&self.dir)?;
+        file.remaining = items.len();
+
+        let mut writer = BufWriter::new(writer);
+        for (item, counter) in items {
+            let bytes = postcard::to_stdvec(&(counter, item))
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+            writer.write_all(&bytes)?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        file.reader = Some(BufReader::new(File::open(&file.path)?));
+        if let Some(head) = file.read_next()? {
+            self.runs.push(Run { head, file });
+        }
+        self.memory.truncate(0);
+        Ok(())
+    }
+}
+
+impl<T, C: Compare<T>> Debug for SpillableStableHeap<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpillableStableHeap")
+            .field("len", &self.len)
+            .field("in_memory", &self.memory.len())
+            .field("runs", &self.runs.len())
+            .field("dir", &self.dir)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SpillableStableHeap, NEXT_RUN};
+    use crate::test_util::scatter;
+    use crate::{compare::KeyComparator, StableBinaryHeap};
+    use std::{format, fs, sync::atomic, vec::Vec};
+
+    #[test]
+    fn test_spillable_heap() {
+        let dir =
+            std::env::temp_dir().join(format!("stable-binary-heap-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let cmp = KeyComparator(|i: &(u32, u32)| i.0);
+        let mut heap = SpillableStableHeap::from_comparator(64, cmp).with_spill_dir(&dir);
+        let mut reference: StableBinaryHeap<_, _> = StableBinaryHeap::from_comparator(cmp);
+
+        for i in 0..2000u32 {
+            let item = (scatter(i, 20), i);
+            heap.push(item).unwrap();
+            reference.push(item);
+            if i % 5 == 0 {
+                assert_eq!(heap.pop().unwrap(), reference.pop());
+            }
+        }
+        assert!(heap.runs() > 10);
+        assert_eq!(heap.len(), reference.len());
+        assert_eq!(heap.peek(), reference.peek());
+
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop().unwrap()).collect();
+        assert_eq!(out, reference.into_sorted_vec());
+        assert_eq!(heap.runs(), 0);
+
+        drop(heap);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spill_keeps_existing_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "stable-binary-heap-test-existing-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+
+        // Occupies the names of the next runs
+        let next = NEXT_RUN.load(atomic::Ordering::Relaxed);
+        let existing: Vec<_> = (next..next + 4)
+            .map(|run| {
+                dir.join(format!(
+                    "stable-binary-heap-{}-{run}.run",
+                    std::process::id()
+                ))
+            })
+            .collect();
+        for path in &existing {
+            fs::write(path, "keep").unwrap();
+        }
+
+        let mut heap = SpillableStableHeap::new(2).with_spill_dir(&dir);
+        for i in 0..10 {
+            heap.push(i).unwrap();
+        }
+        assert!(heap.runs() > 0);
+        let out: Vec<_> = core::iter::from_fn(|| heap.pop().unwrap()).collect();
+        assert_eq!(out, [9, 8, 7, 6, 5, 4, 3, 2, 1, 0]);
+        drop(heap);
+
+        for path in &existing {
+            assert_eq!(fs::read_to_string(path).unwrap(), "keep");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(feature = "stats")]
+use crate::{compare::Compare, counter::Counter, Allocator, StableBinaryHeap};
+
+/// Counts of the operations a heap performed since it was created or its
+/// stats were reset
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Stats {
+    /// Items added to the heap
+    pub pushes: u64,
+    /// Items removed from the top of the heap
+    pub pops: u64,
+    /// Comparisons made while sifting items
+    pub comparisons: u64,
+    /// Levels items were moved up or down while sifting
+    pub sift_distance: u64,
+}
+
+/// Stand-in recording nothing if the `stats` feature is disabled
+#[cfg(not(feature = "stats"))]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Stats;
+
+#[cfg(not(feature = "stats"))]
+impl Stats {
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self
+    }
+
+    #[inline]
+    pub(crate) fn record_push(&mut self) {}
+
+    #[inline]
+    pub(crate) fn record_pops(&mut self, _n: usize) {}
+
+    #[inline]
+    pub(crate) fn record_comparison(&mut self) {}
+
+    #[inline]
+    pub(crate) fn record_sift(&mut self) {}
+}
+
+#[cfg(feature = "stats")]
+impl Stats {
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self {
+            pushes: 0,
+            pops: 0,
+            comparisons: 0,
+            sift_distance: 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record_push(&mut self) {
+        self.pushes += 1;
+    }
+
+    #[inline]
+    pub(crate) fn record_pops(&mut self, n: usize) {
+        self.pops += n as u64;
+    }
+
+    #[inline]
+    pub(crate) fn record_comparison(&mut self) {
+        self.comparisons += 1;
+    }
+
+    #[inline]
+    pub(crate) fn record_sift(&mut self) {
+        self.sift_distance += 1;
+    }
+}
+
+#[cfg(feature = "stats")]
+impl<T, C: Compare<T>, N: Counter, A: Allocator> StableBinaryHeap<T, C, N, A> {
+    /// Returns the operations performed on the heap so far
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    #[inline]
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::new();
+    }
+}
+
+#[cfg(all(test, feature = "stats"))]
+mod tests {
+    use crate::{StableBinaryHeap, Stats};
+
+    #[test]
+    fn test_stats() {
+        let mut heap = StableBinaryHeap::new();
+        heap.push(1);
+        heap.push(2);
+        assert_eq!(
+            heap.stats(),
+            Stats {
+                pushes: 2,
+                pops: 0,
+                comparisons: 1,
+                sift_distance: 1,
+            }
+        );
+
+        heap.extend(3..10);
+        heap.pop_k(3);
+        assert_eq!(heap.stats().pushes, 9);
+        assert_eq!(heap.stats().pops, 3);
+
+        heap.reset_stats();
+        assert_eq!(heap.stats(), Stats::default());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{
+    buffer::{Allocator, Global},
+    compare::{Compare, MaxComparator},
+    counter::Counter,
+    IntoIterSorted, StableBinaryHeap,
+};
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures_core::{FusedStream, Stream};
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> StableBinaryHeap<T, C, N, A> {
+    /// Returns a stream yielding all items in heap order. All items are
+    /// available right away, so the stream never returns `Poll::Pending`
+    #[inline]
+    pub fn into_stream_sorted(self) -> IntoStreamSorted<T, C, N, A> {
+        IntoStreamSorted {
+            inner: self.into_iter_sorted(),
+        }
+    }
+}
+
+pub struct IntoStreamSorted<T, C = MaxComparator, N = usize, A: Allocator = Global> {
+    inner: IntoIterSorted<T, C, N, A>,
+}
+
+// The heap is never pinned structurally
+impl<T, C, N, A: Allocator> Unpin for IntoStreamSorted<T, C, N, A> {}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> Stream for IntoStreamSorted<T, C, N, A> {
+    type Item = T;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Poll::Ready(self.get_mut().inner.next())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter, A: Allocator> FusedStream for IntoStreamSorted<T, C, N, A> {
+    #[inline]
+    fn is_terminated(&self) -> bool {
+        self.inner.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StableBinaryHeap;
+    use alloc::vec::Vec;
+    use core::{
+        pin::Pin,
+        task::{Context, Poll, Waker},
+    };
+    use futures_core::{FusedStream, Stream};
+
+    #[test]
+    fn test_into_stream_sorted() {
+        let mut heap: StableBinaryHeap<_, _> = StableBinaryHeap::new_by_key(|i: &(u32, char)| i.0);
+        heap.extend([(1, 'a'), (3, 'b'), (1, 'c'), (3, 'd'), (2, 'e')]);
+
+        let mut stream = heap.into_stream_sorted();
+        assert_eq!(stream.size_hint(), (5, Some(5)));
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut items = Vec::new();
+        while let Poll::Ready(Some(i)) = Pin::new(&mut stream).poll_next(&mut cx) {
+            items.push(i.1);
+        }
+        assert_eq!(items, ['b', 'd', 'e', 'a', 'c']);
+        assert!(stream.is_terminated());
+    }
+}
@@ -0,0 +1,21 @@
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+/// Scatters `i` over `0..m`, giving reproducible test data with many equal keys
+pub(crate) fn scatter(i: u32, m: u32) -> u32 {
+    i.wrapping_mul(2_654_435_761) % m
+}
+
+/// Returns the items `(key, i)` for `i` in `0..len` with keys scattered over
+/// `0..m`
+pub(crate) fn keyed_items(len: u32, m: u32) -> Vec<(u32, u32)> {
+    (0..len).map(|i| (scatter(i, m), i)).collect()
+}
+
+/// Returns `items` in the order a stable max-heap on the key pops them, so
+/// greatest key first and equal keys in insertion order
+pub(crate) fn sorted_desc(items: impl IntoIterator<Item = (u32, u32)>) -> Vec<(u32, u32)> {
+    let mut sorted: Vec<_> = items.into_iter().collect();
+    sorted.sort_by_key(|i| Reverse(i.0));
+    sorted
+}
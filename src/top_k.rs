@@ -0,0 +1,102 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    counter::Counter,
+    StableBoundedHeap,
+};
+use alloc::vec::Vec;
+use core::fmt::{self, Debug};
+
+/// Collects the `k` greatest items of a stream in O(n log k) time and O(k)
+/// memory. Of equal items the ones inserted first are kept
+#[derive(Clone)]
+pub struct TopK<T, C = MaxComparator, N = usize> {
+    heap: StableBoundedHeap<T, C, N>,
+}
+
+impl<T: Ord> TopK<T> {
+    /// Creates a new collector keeping the `k` greatest items
+    #[inline]
+    pub fn new(k: usize) -> Self {
+        Self::from_comparator(k, MaxComparator)
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> TopK<T, C, N> {
+    /// Creates a new collector keeping the `k` greatest items ordered by `cmp`
+    #[inline]
+    pub fn from_comparator(k: usize, cmp: C) -> Self {
+        Self {
+            heap: StableBoundedHeap::from_comparator(k, cmp),
+        }
+    }
+
+    /// Inserts `item`, discarding the smallest item if more than `k` items
+    /// have been inserted
+    #[inline]
+    pub fn insert(&mut self, item: T) {
+        self.heap.push(item);
+    }
+
+    /// Returns the amount of items collected so far, which is at most `k`
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    #[inline]
+    pub fn k(&self) -> usize {
+        self.heap.bound()
+    }
+
+    /// Returns the collected items, greatest first
+    #[inline]
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.heap.into_sorted_vec()
+    }
+}
+
+impl<T: Debug, C, N: Debug> Debug for TopK<T, C, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TopK").field("heap", &self.heap).finish()
+    }
+}
+
+impl<T, C: Compare<T>, N: Counter> Extend<T> for TopK<T, C, N> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for i in iter {
+            self.insert(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TopK;
+    use crate::{
+        compare::KeyComparator,
+        test_util::{keyed_items, sorted_desc},
+    };
+
+    #[test]
+    fn test_top_k() {
+        let items = keyed_items(1000, 29);
+
+        let mut expected = sorted_desc(items.iter().copied());
+        expected.truncate(10);
+
+        let mut top: TopK<_, _> = TopK::from_comparator(10, KeyComparator(|i: &(u32, u32)| i.0));
+        top.extend(items);
+        assert_eq!(top.len(), 10);
+        assert_eq!(top.into_sorted_vec(), expected);
+
+        let mut top = TopK::new(0);
+        top.insert(1);
+        assert!(top.is_empty());
+    }
+}
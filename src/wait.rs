@@ -0,0 +1,27 @@
+use core::time::Duration;
+use std::sync::{Condvar, MutexGuard, PoisonError};
+
+/// Blocks on `condvar` while `condition` holds for the locked state, at most
+/// for `timeout` if one is given. Returns the guard and whether the wait timed
+/// out with `condition` still holding. Poisoning is ignored like when locking
+pub(crate) fn wait_while<'a, S>(
+    condvar: &Condvar,
+    guard: MutexGuard<'a, S>,
+    timeout: Option<Duration>,
+    condition: impl FnMut(&mut S) -> bool,
+) -> (MutexGuard<'a, S>, bool) {
+    match timeout {
+        Some(timeout) => {
+            let (guard, result) = condvar
+                .wait_timeout_while(guard, timeout, condition)
+                .unwrap_or_else(PoisonError::into_inner);
+            (guard, result.timed_out())
+        }
+        None => {
+            let guard = condvar
+                .wait_while(guard, condition)
+                .unwrap_or_else(PoisonError::into_inner);
+            (guard, false)
+        }
+    }
+}
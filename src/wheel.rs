@@ -0,0 +1,241 @@
+use crate::delay::{DelayBackend, Delayed, HeapBackend};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// Deadline of a `TimingWheel`, which is bucketed by whole ticks
+pub trait Tick: Ord {
+    /// Returns the tick of the deadline, which has to be non-decreasing with
+    /// respect to `Ord`
+    fn tick(&self) -> u64;
+}
+
+macro_rules! impl_tick {
+    ($($t:ty),*) => {
+        $(
+            impl Tick for $t {
+                #[inline]
+                fn tick(&self) -> u64 {
+                    *self as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_tick!(u8, u16, u32, u64, usize);
+
+/// Ticks are milliseconds
+impl Tick for Duration {
+    #[inline]
+    fn tick(&self) -> u64 {
+        u64::try_from(self.as_millis()).unwrap_or(u64::MAX)
+    }
+}
+
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+/// Enough levels to cover all 64 bits of a tick
+const LEVELS: usize = u64::BITS.div_ceil(SLOT_BITS) as usize;
+
+/// Hierarchical timing wheel backend of a `DelayQueue`, e.g.
+/// `DelayQueue::with_backend(TimingWheel::new())`. Pushing is O(1) and items
+/// are moved down the levels at most once per level until they are due,
+/// avoiding the O(log n) sifting of a heap for millions of timers.
+///
+/// Items are bucketed by the tick of their deadline. Once a tick is reached
+/// its items move to a stable heap, which orders deadlines within the same
+/// tick and keeps equal deadlines in the order they were pushed
+#[derive(Clone)]
+pub struct TimingWheel<T, I> {
+    /// `SLOTS` slots per level in insertion order. Level `l` holds the items
+    /// whose tick first differs from `elapsed` in the `l`-th group of
+    /// `SLOT_BITS` bits, in the slot given by that group
+    slots: Vec<Vec<Delayed<T, I>>>,
+    /// Bit `s` of `occupied[l]` is set if slot `s` of level `l` is non empty
+    occupied: [u64; LEVELS],
+    /// All items with a tick up to `elapsed` are in `ready`
+    elapsed: u64,
+    ready: HeapBackend<T, I>,
+    len: usize,
+}
+
+impl<T, I: Tick> TimingWheel<T, I> {
+    /// Creates a new empty timing wheel
+    pub fn new() -> Self {
+        Self {
+            slots: (0..LEVELS * SLOTS).map(|_| Vec::new()).collect(),
+            occupied: [0; LEVELS],
+            elapsed: 0,
+            ready: HeapBackend::new(),
+            len: 0,
+        }
+    }
+
+    fn insert(&mut self, delayed: Delayed<T, I>) {
+        let tick = delayed.deadline.tick();
+        if tick <= self.elapsed {
+            self.ready.push(delayed.item, delayed.deadline);
+            return;
+        }
+
+        let significant =
+            u64::BITS - 1 - ((tick ^ self.elapsed) | (SLOTS as u64 - 1)).leading_zeros();
+        let level = (significant / SLOT_BITS) as usize;
+        let slot = ((tick >> (level as u32 * SLOT_BITS)) as usize) & (SLOTS - 1);
+        self.slots[level * SLOTS + slot].push(delayed);
+        self.occupied[level] |= 1 << slot;
+    }
+
+    /// Returns the level, slot and first tick of the slot holding the earliest
+    /// items. Items of lower levels are always earlier than those of higher
+    /// levels
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        let level = self.occupied.iter().position(|occupied| *occupied != 0)?;
+        let shift = level as u32 * SLOT_BITS;
+        let current = ((self.elapsed >> shift) as usize) & (SLOTS - 1);
+        let offset = self.occupied[level]
+            .rotate_right(current as u32)
+            .trailing_zeros();
+        let slot = (current + offset as usize) % SLOTS;
+
+        let level_mask = (1u128 << (shift + SLOT_BITS)) - 1;
+        let level_start = (u128::from(self.elapsed) & !level_mask) as u64;
+        Some((level, slot, level_start + ((slot as u64) << shift)))
+    }
+
+    /// Processes slots up to tick `now` until there are ready items
+    fn advance(&mut self, now: u64) {
+        while self.ready.is_empty() {
+            let Some((level, slot, start)) = self.next_expiration() else {
+                return;
+            };
+            if start > now {
+                return;
+            }
+
+            self.elapsed = self.elapsed.max(start);
+            self.occupied[level] &= !(1 << slot);
+            let items = core::mem::take(&mut self.slots[level * SLOTS + slot]);
+            for delayed in items {
+                self.insert(delayed);
+            }
+        }
+    }
+}
+
+impl<T, I: Tick> DelayBackend<T, I> for TimingWheel<T, I> {
+    #[inline]
+    fn push(&mut self, item: T, deadline: I) {
+        self.insert(Delayed { item, deadline });
+        self.len += 1;
+    }
+
+    fn pop_next_due(&mut self, now: &I) -> Option<T> {
+        self.advance(now.tick());
+        let item = self.ready.pop_next_due(now)?;
+        self.len -= 1;
+        Some(item)
+    }
+
+    fn peek(&self) -> Option<(&T, &I)> {
+        if let Some(top) = self.ready.peek() {
+            return Some(top);
+        }
+
+        let (level, slot, _) = self.next_expiration()?;
+        self.slots[level * SLOTS + slot]
+            .iter()
+            .min_by(|a, b| a.deadline.cmp(&b.deadline))
+            .map(|i| (&i.item, &i.deadline))
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        for slot in &mut self.slots {
+            slot.clear();
+        }
+        self.occupied = [0; LEVELS];
+        self.elapsed = 0;
+        self.ready.clear();
+        self.len = 0;
+    }
+
+    #[inline]
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a T, &'a I)>
+    where
+        T: 'a,
+        I: 'a,
+    {
+        self.ready
+            .iter()
+            .chain(self.slots.iter().flatten().map(|i| (&i.item, &i.deadline)))
+    }
+}
+
+impl<T, I: Tick> Default for TimingWheel<T, I> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TimingWheel;
+    use crate::test_util::scatter;
+    use crate::DelayQueue;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[test]
+    fn test_timing_wheel() {
+        let mut wheel = DelayQueue::with_backend(TimingWheel::new());
+        let mut heap = DelayQueue::new();
+
+        let mut now = 0u64;
+        for i in 0..5000u64 {
+            // Spread deadlines over several levels with many ties
+            let deadline = now + u64::from(scatter(i as u32, 7)) * 10u64.pow((i % 6) as u32);
+            wheel.push(i, deadline);
+            heap.push(i, deadline);
+
+            if i % 3 == 0 {
+                now += 50;
+                let due: Vec<_> = wheel.pop_due(&now).collect();
+                assert_eq!(due, heap.pop_due(&now).collect::<Vec<_>>());
+                assert_eq!(wheel.peek(), heap.peek());
+            }
+        }
+
+        assert_eq!(wheel.len(), heap.len());
+        let out: Vec<_> = wheel.pop_due(&u64::MAX).collect();
+        assert_eq!(out, heap.pop_due(&u64::MAX).collect::<Vec<_>>());
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn test_timing_wheel_sub_tick() {
+        let mut queue = DelayQueue::with_backend(TimingWheel::new());
+        queue.extend([
+            ('a', Duration::from_micros(5_300)),
+            ('b', Duration::from_micros(5_100)),
+            ('c', Duration::from_micros(5_300)),
+            ('d', Duration::from_millis(900)),
+        ]);
+        assert_eq!(queue.next_deadline(), Some(&Duration::from_micros(5_100)));
+        assert_eq!(
+            queue
+                .pop_due(&Duration::from_micros(5_200))
+                .collect::<Vec<_>>(),
+            ['b']
+        );
+        assert_eq!(
+            queue.pop_due(&Duration::from_secs(1)).collect::<Vec<_>>(),
+            ['a', 'c', 'd']
+        );
+    }
+}
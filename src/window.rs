@@ -0,0 +1,210 @@
+use crate::{
+    compare::{Compare, MaxComparator},
+    StableBinaryHeap,
+};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+};
+
+/// Keeps the `k` greatest of the last `window` pushed items. Items older than
+/// the window expire automatically. Of equal items the older ones rank higher
+/// unless `C` breaks ties differently
+#[derive(Clone)]
+pub struct StableWindowTopK<T, C = MaxComparator> {
+    /// Items of the window plus expired ones that haven't been removed yet
+    heap: StableBinaryHeap<WindowItem<T>, WindowComparator<C>>,
+    k: usize,
+    window: u64,
+    /// Sequence number of the next pushed item
+    seq: u64,
+}
+
+#[derive(Clone, Debug)]
+struct WindowItem<T> {
+    item: T,
+    seq: u64,
+}
+
+/// Orders window items by `C`, ignoring their sequence number
+#[derive(Clone, Default)]
+struct WindowComparator<C>(C);
+
+impl<T, C: Compare<T>> Compare<WindowItem<T>> for WindowComparator<C> {
+    #[inline]
+    fn compare(&self, a: &WindowItem<T>, b: &WindowItem<T>) -> Ordering {
+        self.0.compare(&a.item, &b.item)
+    }
+
+    #[inline]
+    fn compare_parts<N: Ord>(
+        &self,
+        a: &WindowItem<T>,
+        a_counter: &N,
+        b: &WindowItem<T>,
+        b_counter: &N,
+    ) -> Ordering {
+        self.0.compare_parts(&a.item, a_counter, &b.item, b_counter)
+    }
+}
+
+impl<T: Ord> StableWindowTopK<T> {
+    /// Creates a new collector keeping the `k` greatest of the last `window` items
+    #[inline]
+    pub fn new(k: usize, window: usize) -> Self {
+        Self::from_comparator(k, window, MaxComparator)
+    }
+}
+
+impl<T, C: Compare<T>> StableWindowTopK<T, C> {
+    /// Creates a new collector keeping the `k` greatest of the last `window`
+    /// items ordered by `cmp`
+    #[inline]
+    pub fn from_comparator(k: usize, window: usize, cmp: C) -> Self {
+        Self {
+            heap: StableBinaryHeap::from_comparator(WindowComparator(cmp)),
+            k,
+            window: window as u64,
+            seq: 0,
+        }
+    }
+
+    /// Pushes a new item, letting the oldest item of the window expire if the
+    /// window is full
+    pub fn push(&mut self, item: T) {
+        let seq = self.seq;
+        self.seq += 1;
+        if self.window == 0 {
+            return;
+        }
+        self.heap.push(WindowItem { item, seq });
+
+        // Expired items are removed lazily once they reach the top or once the
+        // heap holds twice the window
+        let first_valid = self.first_valid();
+        while self.heap.peek().is_some_and(|i| i.seq < first_valid) {
+            self.heap.pop();
+        }
+        if self.heap.len() as u64 > 2 * self.window {
+            self.heap.retain(|i| i.seq >= first_valid);
+        }
+    }
+
+    /// Returns the `k` greatest items of the window, greatest first
+    pub fn top_k(&self) -> impl Iterator<Item = &T> {
+        let first_valid = self.first_valid();
+        self.heap
+            .iter_sorted()
+            .filter(move |i| i.seq >= first_valid)
+            .take(self.k)
+            .map(|i| &i.item)
+    }
+
+    /// Returns the greatest item of the window
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.top_k().next()
+    }
+
+    /// Returns the amount of items in the window
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.seq.min(self.window) as usize
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum amount of items returned by `top_k`
+    #[inline]
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the amount of most recent items considered
+    #[inline]
+    pub fn window(&self) -> usize {
+        self.window as usize
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.seq = 0;
+    }
+
+    /// Sequence number of the oldest item in the window
+    #[inline]
+    fn first_valid(&self) -> u64 {
+        self.seq.saturating_sub(self.window)
+    }
+}
+
+impl<T: Debug, C: Compare<T>> Debug for StableWindowTopK<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableWindowTopK")
+            .field("top_k", &DebugTopK(self))
+            .field("k", &self.k)
+            .field("window", &self.window)
+            .finish()
+    }
+}
+
+struct DebugTopK<'a, T, C>(&'a StableWindowTopK<T, C>);
+
+impl<T: Debug, C: Compare<T>> Debug for DebugTopK<'_, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.top_k()).finish()
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for StableWindowTopK<T, C> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for i in iter {
+            self.push(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StableWindowTopK;
+    use crate::{
+        compare::KeyComparator,
+        test_util::{keyed_items, sorted_desc},
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_window_top_k() {
+        let items = keyed_items(1000, 31);
+
+        let mut top = StableWindowTopK::from_comparator(5, 50, KeyComparator(|i: &(u32, u32)| i.0));
+        for (n, item) in items.iter().enumerate() {
+            top.push(*item);
+
+            let mut expected = sorted_desc(items[(n + 1).saturating_sub(50)..=n].iter().copied());
+            expected.truncate(5);
+
+            assert_eq!(top.top_k().copied().collect::<Vec<_>>(), expected);
+            assert!(top.heap.len() <= 100);
+        }
+        assert_eq!(top.len(), 50);
+    }
+
+    #[test]
+    fn test_window_top_k_empty() {
+        let mut top = StableWindowTopK::new(3, 0);
+        top.push(1);
+        assert!(top.is_empty());
+        assert_eq!(top.peek(), None);
+
+        let mut top = StableWindowTopK::new(0, 3);
+        top.push(1);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top.peek(), None);
+    }
+}